@@ -1,10 +1,14 @@
 use std::error::Error;
 
 use clap::Args;
+use colored::Colorize;
 
 use crate::{
-    tree::total_tree::TreeTotal,
-    utils::{print_tree, AnalyzeOptions, Lookup},
+    calc::Categorizer,
+    import::{ImportArgs, ImportOptions},
+    period::PeriodArgs,
+    tree::{total_tree::TreeTotal, TotalsArgs, TotalsOptions},
+    utils::{format_with_color, print_tree, AnalyzeOptions},
     Tree,
 };
 
@@ -20,6 +24,24 @@ pub struct AnalyzeArgs {
     hide_ignored: bool,
     #[arg(short, long)]
     depth: Option<usize>,
+    /// Show the amounts of converted items in their original currency.
+    #[arg(long)]
+    show_original: bool,
+    /// Group the printed items by their canonical payee instead of the full description.
+    #[arg(long)]
+    group_by_payee: bool,
+    /// Do not write the categories back to the file. The lookup is still updated.
+    #[arg(long)]
+    dry_run: bool,
+    /// Show the totals for each account.
+    #[arg(long)]
+    by_account: bool,
+    #[command(flatten)]
+    periods: PeriodArgs,
+    #[command(flatten)]
+    totals: TotalsArgs,
+    #[command(flatten)]
+    import: ImportArgs,
 }
 
 impl From<&AnalyzeArgs> for AnalyzeOptions {
@@ -29,19 +51,115 @@ impl From<&AnalyzeArgs> for AnalyzeOptions {
             value.print_items,
             value.hide_ignored,
             value.depth,
+            value.show_original,
+            value.group_by_payee,
         )
     }
 }
 
-pub fn run(args: &AnalyzeArgs, lookup: &mut Lookup) -> Result<(), Box<dyn Error>> {
+pub fn run(args: &AnalyzeArgs, categorizer: &mut Categorizer) -> Result<(), Box<dyn Error>> {
     let opts: AnalyzeOptions = args.into();
 
-    let tree = Tree::load_from_file(&args.filename, lookup)?;
-    let total = TreeTotal::create_from(&tree, opts.ignored_categories());
+    let import: ImportOptions = (&args.import).try_into()?;
+    let totals: TotalsOptions = (&args.totals).try_into()?;
+    let Some(granularity) = args.periods.granularity() else {
+        let tree =
+            Tree::load_from_file(&args.filename, categorizer, &import, &totals, !args.dry_run)?;
+        categorizer.check()?;
+        return print_details(&tree, args, &opts, &totals);
+    };
 
-    println!("Details for: {}", tree.get_name());
-    print_tree(&tree, &total, &opts);
+    let trees = Tree::load_by_period(
+        std::slice::from_ref(&args.filename),
+        categorizer,
+        &import,
+        &totals,
+        !args.dry_run,
+        granularity,
+        (&args.periods).into(),
+    )?;
+    categorizer.check()?;
+    for (i, tree) in trees.iter().filter(|t| !t.is_empty()).enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print_details(tree, args, &opts, &totals)?;
+    }
+
+    Ok(())
+}
+
+fn print_details(
+    tree: &Tree,
+    args: &AnalyzeArgs,
+    opts: &AnalyzeOptions,
+    totals: &TotalsOptions,
+) -> Result<(), Box<dyn Error>> {
+    let total = TreeTotal::create_from(tree, opts.ignored_categories());
+
+    match tree.get_period() {
+        Some(period) => println!(
+            "Details for: {} ({} to {})",
+            tree.get_name(),
+            period.start(),
+            period.end()
+        ),
+        None => println!("Details for: {}", tree.get_name()),
+    }
+    print_tree(tree, &total, opts);
     println!("{total}");
 
+    if !tree.get_excluded().is_empty() {
+        println!();
+        println!("Excluded:");
+        for record in tree.get_excluded() {
+            let reason = totals
+                .exclusions()
+                .find(record)
+                .and_then(|e| e.reason().clone())
+                .unwrap_or_default();
+            println!(
+                "  {} {:<40}{:>10}  {} {}",
+                record.date().to_string().cyan(),
+                record.description(),
+                format_with_color(record.get_amount()),
+                record.id().dimmed(),
+                reason
+            );
+        }
+    }
+
+    if !tree.get_refunds().is_empty() {
+        println!();
+        println!("Refunds:");
+        for refund in tree.get_refunds() {
+            let (original, refund) = (refund.original(), refund.refund());
+            println!(
+                "  {} {:<40}{:>10}  refunds {} {} {}",
+                refund.date().to_string().cyan(),
+                refund.description(),
+                format_with_color(refund.get_amount()),
+                original.date().to_string().cyan(),
+                format_with_color(original.get_amount()),
+                original.category().as_deref().unwrap_or_default()
+            );
+        }
+    }
+
+    if args.by_account {
+        for (account, total) in TreeTotal::create_by_account(tree, opts.ignored_categories()) {
+            println!();
+            println!(
+                "Account: {}",
+                if account.is_empty() {
+                    "Unknown"
+                } else {
+                    &account
+                }
+            );
+            println!("{total}");
+        }
+    }
+
     Ok(())
 }
@@ -1,33 +1,497 @@
-use std::{
-    collections::HashMap,
-    error::Error,
-    io::{self, Write},
-};
+pub mod prompt;
+
+use std::{collections::BTreeSet, error::Error};
 
+use clap::ValueEnum;
 use colored::Colorize;
+use rust_decimal::Decimal;
+
+use crate::{
+    calc::prompt::{Answer, Prompt},
+    classify::{Classifier, Suggestion},
+    normalize::Normalizer,
+    rules::{Rule, Rules},
+    splits,
+    utils::{format_with_color, Lookup},
+    Record,
+};
+
+/// Number of suggestions shown when asking for a category.
+const SUGGESTIONS: usize = 3;
+
+/// Tag set on records that were categorized automatically by the classifier.
+pub const REVIEW: &str = "review";
+
+/// Describe the records the user is asked a category for.
+fn describe(records: &[&Record]) -> String {
+    let first = records[0];
+    if records.len() == 1 {
+        return format!(
+            "{} - {} {}",
+            first.date().to_string().cyan(),
+            first.description().green(),
+            format_with_color(first.get_amount()),
+        );
+    }
+
+    let total: Decimal = records.iter().map(|r| r.get_amount()).sum();
+    let from = records
+        .iter()
+        .map(|r| r.date())
+        .min()
+        .unwrap_or(first.date());
+    let to = records
+        .iter()
+        .map(|r| r.date())
+        .max()
+        .unwrap_or(first.date());
+    format!(
+        "{} records like {} - total {}, from {} to {}",
+        records.len(),
+        first.description().green(),
+        format_with_color(total),
+        from.to_string().cyan(),
+        to.to_string().cyan(),
+    )
+}
+
+/// What to do with records that have no category, instead of asking the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum NonInteractive {
+    /// Stop before any output, listing the uncategorized descriptions.
+    Fail,
+    /// Count the records in a placeholder category.
+    Uncategorized,
+    /// Leave the records out.
+    Skip,
+}
+
+/// Where the category for a record comes from.
+#[derive(Debug, Clone, Copy)]
+pub enum CategorySource<'a> {
+    /// The category is already in the statement file.
+    File,
+    /// The category comes from a rule, given with its position in the rules file.
+    Rule(usize, &'a Rule),
+    Lookup,
+    /// The category is suggested by the classifier with enough confidence.
+    Classifier(f64),
+    /// No category could be found, so the user has to be asked.
+    Missing,
+}
+
+/// An answer given while categorizing, with what it changed so it can be undone.
+struct Answered {
+    /// Position of the group the answer was given for.
+    position: usize,
+    /// Group and position of the records that got a category.
+    changed: Vec<(usize, usize)>,
+    /// Payee and category stored in the lookup.
+    stored: Option<(String, String)>,
+    /// Descriptions added to the missing ones.
+    skipped: Vec<String>,
+}
+
+impl Answered {
+    fn new(position: usize) -> Self {
+        Self {
+            position,
+            changed: Vec::new(),
+            stored: None,
+            skipped: Vec::new(),
+        }
+    }
+}
+
+/// Assigns categories to records, using the rules first and then the lookup,
+/// before falling back to asking the user.
+///
+/// The lookup is keyed on the canonical payee of the records. Entries keyed on
+/// the full description, from before the payee was used, are still honored.
+///
+/// A classifier trained on the lookup suggests categories when asking the user.
+/// If a threshold is set, suggestions at least that confident are used directly
+/// and the record is tagged for review instead.
+///
+/// When running non-interactively, the descriptions of the records left without
+/// a category are collected, so they can be reported at the end.
+#[derive(Debug, Default)]
+pub struct Categorizer {
+    lookup: Lookup,
+    rules: Rules,
+    normalizer: Normalizer,
+    classifier: Classifier,
+    threshold: Option<f64>,
+    non_interactive: Option<(NonInteractive, String)>,
+    missing: BTreeSet<String>,
+}
 
-use crate::Record;
+impl Categorizer {
+    pub fn new(lookup: Lookup, rules: Rules, normalizer: Normalizer) -> Self {
+        let mut classifier = Classifier::default();
+        for (key, category) in lookup.iter() {
+            classifier.learn(&normalizer.normalize(key), category);
+        }
+
+        Self {
+            lookup,
+            rules,
+            normalizer,
+            classifier,
+            threshold: None,
+            non_interactive: None,
+            missing: BTreeSet::new(),
+        }
+    }
+
+    pub fn lookup(&self) -> &Lookup {
+        &self.lookup
+    }
+
+    pub fn lookup_mut(&mut self) -> &mut Lookup {
+        &mut self.lookup
+    }
+
+    /// Set the confidence above which suggestions are used without asking.
+    pub fn set_threshold(&mut self, threshold: Option<f64>) {
+        self.threshold = threshold;
+    }
+
+    /// Never ask the user, and handle records without a category according to the
+    /// policy. The category is used as placeholder with [`NonInteractive::Uncategorized`].
+    pub fn set_non_interactive(&mut self, policy: Option<NonInteractive>, category: String) {
+        self.non_interactive = policy.map(|p| (p, category));
+    }
+
+    /// The category to count uncategorized records in, if they should not be left out.
+    pub fn placeholder(&self) -> Option<&str> {
+        match &self.non_interactive {
+            Some((NonInteractive::Uncategorized, category)) => Some(category),
+            _ => None,
+        }
+    }
+
+    /// The descriptions of the records left without a category.
+    pub fn missing(&self) -> &BTreeSet<String> {
+        &self.missing
+    }
+
+    /// Fail if records were left without a category and the policy is to fail.
+    pub fn check(&self) -> Result<(), Box<dyn Error>> {
+        match self.non_interactive {
+            Some((NonInteractive::Fail, _)) if !self.missing.is_empty() => {
+                Err(self.missing_error())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// List the descriptions of the records left without a category on stderr,
+    /// and get the error to exit with.
+    pub fn missing_error(&self) -> Box<dyn Error> {
+        for description in self.missing.iter() {
+            eprintln!("Uncategorized: {}", description.yellow());
+        }
+        format!("{} descriptions left uncategorized", self.missing.len()).into()
+    }
+
+    /// Set the canonical payee on the record.
+    pub fn normalize(&self, record: &mut Record) {
+        record.set_payee(self.normalizer.normalize(record.description()));
+    }
 
-/// Get the catogory, either from the lookup or by asking the user.
-pub fn get_category(
-    record: &Record,
-    lookup: &HashMap<String, String>,
-) -> Result<String, Box<dyn Error>> {
-    if let Some(category) = lookup.get(record.description()) {
-        return Ok(category.to_owned());
+    /// Set the categories of the records that do not have one yet, asking the user
+    /// for those that cannot be categorized automatically. Records skipped by the
+    /// user, or when running non-interactively, are left without a category and
+    /// reported as missing.
+    pub fn categorize_all(&mut self, records: &mut [&mut Record]) -> Result<(), Box<dyn Error>> {
+        let mut groups: Vec<Vec<&mut Record>> =
+            records.iter_mut().map(|r| vec![&mut **r]).collect();
+        self.categorize_groups(&mut groups)
     }
 
-    print!(
-        "Category missing for {} - {}. Enter new category: ",
-        record.date().cyan(),
-        record.description().green(),
-    );
-    io::stdout().flush()?;
+    /// Like [`Categorizer::categorize_all`], but the user is asked once for all the
+    /// records in a group that cannot be categorized automatically.
+    ///
+    /// Answers are stored in the lookup as soon as they are given, so they are kept
+    /// if the session is interrupted, and the later records with the same payee
+    /// are categorized without asking again.
+    pub fn categorize_groups(
+        &mut self,
+        groups: &mut [Vec<&mut Record>],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut prompt: Option<Prompt> = None;
+        let mut answers: Vec<Answered> = Vec::new();
 
-    let mut category = String::new();
-    io::stdin().read_line(&mut category)?;
-    let c = category.trim().to_string();
-    category.clear();
+        let mut i = 0;
+        while i < groups.len() {
+            // Earlier answers may have taught the lookup the category
+            for (j, record) in groups[i].iter_mut().enumerate() {
+                if record.category().is_none() && self.categorize(record) {
+                    if let Some(answer) = answers.last_mut() {
+                        answer.changed.push((i, j));
+                    }
+                }
+            }
 
-    Ok(c)
+            let pending: Vec<usize> = (0..groups[i].len())
+                .filter(|j| groups[i][*j].category().is_none())
+                .collect();
+            if pending.is_empty() {
+                i += 1;
+                continue;
+            }
+            if self.non_interactive.is_some() {
+                for j in pending {
+                    self.missing.insert(groups[i][j].description().to_owned());
+                }
+                i += 1;
+                continue;
+            }
+
+            let prompt = match prompt.as_mut() {
+                Some(prompt) => prompt,
+                None => prompt.insert(Prompt::new(self.categories())?),
+            };
+            let records: Vec<&Record> = pending.iter().map(|j| &*groups[i][*j]).collect();
+            let suggestions: Vec<(String, String)> = self
+                .suggest(records[0])
+                .into_iter()
+                .map(|s| {
+                    let confidence = format!("{:.0}%", s.confidence() * 100.0);
+                    (s.category().to_owned(), confidence)
+                })
+                .collect();
+            let question = format!("Category missing for {}", describe(&records));
+            let mut answered = Answered::new(i);
+            match prompt.ask(&question, &suggestions)? {
+                Answer::Category(category) => {
+                    for j in pending.iter() {
+                        groups[i][*j].set_category(category.clone());
+                        answered.changed.push((i, *j));
+                    }
+                }
+                Answer::All(category) => {
+                    let payee = groups[i][pending[0]].get_payee().to_owned();
+                    for (g, group) in groups.iter_mut().enumerate().skip(i) {
+                        for (j, record) in group.iter_mut().enumerate() {
+                            if record.category().is_none() && record.get_payee() == payee {
+                                record.set_category(category.clone());
+                                answered.changed.push((g, j));
+                            }
+                        }
+                    }
+                }
+                Answer::Skip => {
+                    for j in pending.iter() {
+                        let description = groups[i][*j].description().to_owned();
+                        if self.missing.insert(description.clone()) {
+                            answered.skipped.push(description);
+                        }
+                    }
+                }
+                Answer::Undo => {
+                    match answers.pop() {
+                        Some(answer) => {
+                            for (g, j) in answer.changed {
+                                groups[g][j].clear_category();
+                            }
+                            for description in answer.skipped {
+                                self.missing.remove(&description);
+                            }
+                            if let Some((payee, category)) = answer.stored {
+                                self.lookup.remove(&payee);
+                                self.classifier.unlearn(&payee, &category);
+                            }
+                            i = answer.position;
+                        }
+                        None => println!("Nothing to undo"),
+                    }
+                    continue;
+                }
+            }
+
+            let record = &*groups[i][pending[0]];
+            if let Some(category) = record.category() {
+                if !self.lookup.contains_key(record.get_payee()) {
+                    self.remember(record);
+                    if self.lookup.contains_key(record.get_payee()) {
+                        answered.stored = Some((record.get_payee().to_owned(), category.clone()));
+                    }
+                }
+            }
+            answers.push(answered);
+            i += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Set the category of a record from the rules, the lookup or a confident
+    /// suggestion. Returns false if none of them applies.
+    pub fn categorize(&self, record: &mut Record) -> bool {
+        if let Some((_, rule)) = self.rules.find(record) {
+            record.set_category(rule.category().to_owned());
+            return true;
+        }
+        if let Some(category) = self.lookup_category(record) {
+            record.set_category(category.to_owned());
+            return true;
+        }
+        if let Some(suggestion) = self.confident(&self.suggest(record)) {
+            record.set_category(suggestion.category().to_owned());
+            record.add_tag(REVIEW);
+            return true;
+        }
+
+        false
+    }
+
+    /// All the categories in the lookup and the rules.
+    pub fn categories(&self) -> BTreeSet<String> {
+        self.lookup
+            .values()
+            .chain(self.rules.categories())
+            .filter(|c| !c.is_empty())
+            .cloned()
+            .collect()
+    }
+
+    /// Get the most likely categories for the record according to the classifier.
+    pub fn suggest(&self, record: &Record) -> Vec<Suggestion> {
+        self.classifier.suggest(record.get_payee(), SUGGESTIONS)
+    }
+
+    fn confident<'a>(&self, suggestions: &'a [Suggestion]) -> Option<&'a Suggestion> {
+        let threshold = self.threshold?;
+        suggestions.first().filter(|s| *s.confidence() >= threshold)
+    }
+
+    fn lookup_category(&self, record: &Record) -> Option<&String> {
+        self.lookup
+            .get(record.get_payee())
+            .or_else(|| self.lookup.get(record.description()))
+    }
+
+    /// Store the category of the record in the lookup, unless it is covered by a
+    /// rule, still has to be reviewed, is split, or the lookup already has an entry for it.
+    pub fn remember(&mut self, record: &Record) {
+        self.store(record, false);
+    }
+
+    /// Like [`Categorizer::remember`], but replacing the entry already in the lookup.
+    pub fn overwrite(&mut self, record: &Record) {
+        self.store(record, true);
+    }
+
+    /// Whether the category of the record can be stored in the lookup, which is
+    /// not the case when it is covered by a rule, still has to be reviewed or is split.
+    pub fn storable(&self, record: &Record) -> bool {
+        match record.category() {
+            Some(category) => {
+                self.rules.find(record).is_none()
+                    && !record.has_tag(REVIEW)
+                    && splits::parse(category).is_none()
+            }
+            None => false,
+        }
+    }
+
+    fn store(&mut self, record: &Record, overwrite: bool) {
+        let Some(category) = record.category() else {
+            return;
+        };
+        if !self.storable(record) || (!overwrite && self.lookup.contains_key(record.get_payee())) {
+            return;
+        }
+
+        let previous = self
+            .lookup
+            .insert(record.get_payee().to_owned(), category.to_owned());
+        if previous.as_ref() != Some(category) {
+            if let Some(previous) = previous {
+                self.classifier.unlearn(record.get_payee(), &previous);
+            }
+            self.classifier.learn(record.get_payee(), category);
+        }
+    }
+
+    /// Get the category the rules or the lookup give the record, ignoring the
+    /// category it already has.
+    pub fn known_category(&self, record: &Record) -> Option<String> {
+        match self.rules.find(record) {
+            Some((_, rule)) => Some(rule.category().to_owned()),
+            None => self.lookup_category(record).cloned(),
+        }
+    }
+
+    /// Whether the user can be asked for categories.
+    pub fn interactive(&self) -> bool {
+        self.non_interactive.is_none()
+    }
+
+    /// Explain where the category of a record comes from.
+    pub fn explain(&self, record: &Record) -> (Option<String>, CategorySource<'_>) {
+        if let Some(category) = record.category() {
+            return (Some(category.to_owned()), CategorySource::File);
+        }
+        if let Some((index, rule)) = self.rules.find(record) {
+            return (
+                Some(rule.category().to_owned()),
+                CategorySource::Rule(index, rule),
+            );
+        }
+        match self.lookup_category(record) {
+            Some(category) => (Some(category.to_owned()), CategorySource::Lookup),
+            None => match self.confident(&self.suggest(record)) {
+                Some(suggestion) => (
+                    Some(suggestion.category().to_owned()),
+                    CategorySource::Classifier(*suggestion.confidence()),
+                ),
+                None => (None, CategorySource::Missing),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use super::*;
+
+    fn record(description: &str) -> Record {
+        Record::new(
+            NaiveDate::from_ymd_opt(2023, 1, 31).unwrap(),
+            description.to_string(),
+            Some(Decimal::ONE),
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn collect_missing_when_non_interactive() {
+        let lookup = Lookup::from([("tesco stores".to_string(), "Food".to_string())]);
+        let mut categorizer = Categorizer::new(lookup, Rules::default(), Normalizer::default());
+        categorizer.set_non_interactive(Some(NonInteractive::Fail), String::new());
+
+        let mut known = record("TESCO STORES 3345");
+        let mut unknown = record("NETFLIX");
+        categorizer.normalize(&mut known);
+        categorizer.normalize(&mut unknown);
+        categorizer
+            .categorize_all(&mut [&mut known, &mut unknown])
+            .unwrap();
+
+        assert_eq!(Some("Food"), known.category().as_deref());
+        assert_eq!(None, *unknown.category());
+
+        assert!(categorizer.check().is_err());
+        assert_eq!(
+            vec!["NETFLIX"],
+            categorizer.missing().iter().collect::<Vec<_>>()
+        );
+    }
 }
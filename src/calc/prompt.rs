@@ -0,0 +1,194 @@
+use std::{collections::BTreeSet, error::Error};
+
+use colored::Colorize;
+use rustyline::{
+    completion::Completer, error::ReadlineError, history::DefaultHistory, Context, Editor, Helper,
+    Highlighter, Hinter, Validator,
+};
+
+const HELP: &str = "Enter a category, or the number of a choice. Press tab to complete.
+  :a <category>  apply the category to all the records with the same payee
+  :s             skip the record
+  :u             undo the previous answer
+  :l             list the existing categories";
+
+/// Answer given by the user when asked for the category of a record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Category(String),
+    /// The category for all the records with the same payee.
+    All(String),
+    Skip,
+    Undo,
+}
+
+/// Completes the `/` separated category paths, one level at a time.
+#[derive(Helper, Hinter, Highlighter, Validator)]
+struct CategoryHelper {
+    categories: BTreeSet<String>,
+}
+
+impl Completer for CategoryHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        // Complete the argument of commands like `:a`
+        let start = match line.starts_with(':') {
+            true => line[..pos].find(' ').map(|i| i + 1).unwrap_or(pos),
+            false => 0,
+        };
+        Ok((start, complete_path(&self.categories, &line[start..pos])))
+    }
+}
+
+/// Get the categories, or their parents, at the same depth as the input and starting with it.
+fn complete_path(categories: &BTreeSet<String>, input: &str) -> Vec<String> {
+    let depth = input.split('/').count();
+    let input = input.to_lowercase();
+
+    categories
+        .iter()
+        .filter_map(|c| {
+            let mut parts = c.split('/');
+            let path = parts.by_ref().take(depth).collect::<Vec<&str>>();
+            (path.len() == depth).then(|| path.join("/"))
+        })
+        .filter(|c| c.to_lowercase().starts_with(&input))
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect()
+}
+
+/// Interactive prompt asking the user for categories.
+pub struct Prompt {
+    editor: Editor<CategoryHelper, DefaultHistory>,
+}
+
+impl Prompt {
+    pub fn new(categories: BTreeSet<String>) -> Result<Self, Box<dyn Error>> {
+        let mut editor = Editor::new()?;
+        editor.set_helper(Some(CategoryHelper { categories }));
+        Ok(Self { editor })
+    }
+
+    fn categories(&self) -> &BTreeSet<String> {
+        // The helper is always set in `new`
+        &self.editor.helper().unwrap().categories
+    }
+
+    /// Ask for the category of the records described by the question, until a valid
+    /// answer is given. The choices are categories with a note, which can be picked
+    /// by their number.
+    pub fn ask(
+        &mut self,
+        question: &str,
+        choices: &[(String, String)],
+    ) -> Result<Answer, Box<dyn Error>> {
+        println!("{question}");
+        for (i, (category, note)) in choices.iter().enumerate() {
+            println!("  {}) {} ({note})", i + 1, category.yellow());
+        }
+
+        loop {
+            let line = match self.editor.readline("Category (? for help): ") {
+                Ok(line) => line,
+                Err(ReadlineError::Eof) | Err(ReadlineError::Interrupted) => {
+                    return Err(
+                        "No input left while asking for a category, use --non-interactive to run without asking".into(),
+                    )
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let line = line.trim();
+            if !line.is_empty() {
+                self.editor.add_history_entry(line)?;
+            }
+
+            let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+            let answer = match command {
+                "" => None,
+                "?" | ":h" => {
+                    println!("{HELP}");
+                    None
+                }
+                ":l" => {
+                    for category in self.categories() {
+                        println!("  {category}");
+                    }
+                    None
+                }
+                ":s" => Some(Answer::Skip),
+                ":u" => Some(Answer::Undo),
+                ":a" => self.resolve(argument.trim(), choices)?.map(Answer::All),
+                _ if command.starts_with(':') => {
+                    println!("Unknown command {command}\n{HELP}");
+                    None
+                }
+                _ => self.resolve(line, choices)?.map(Answer::Category),
+            };
+
+            if let Some(answer) = answer {
+                return Ok(answer);
+            }
+        }
+    }
+
+    /// Turn the input into a category, asking for confirmation if it is a new one.
+    fn resolve(
+        &mut self,
+        input: &str,
+        choices: &[(String, String)],
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        if let Ok(n) = input.parse::<usize>() {
+            return match choices.get(n.wrapping_sub(1)) {
+                Some((category, _)) => Ok(Some(category.to_owned())),
+                None => {
+                    println!("No choice {n}");
+                    Ok(None)
+                }
+            };
+        }
+        if input.is_empty() {
+            return Ok(None);
+        }
+        if self.categories().contains(input) {
+            return Ok(Some(input.to_owned()));
+        }
+
+        let confirm = self.editor.readline(&format!(
+            "{} is a new category, create it? [y/N] ",
+            input.yellow()
+        ))?;
+        if !confirm.trim().eq_ignore_ascii_case("y") {
+            return Ok(None);
+        }
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.categories.insert(input.to_owned());
+        }
+        Ok(Some(input.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn complete_one_level_at_a_time() {
+        let categories = BTreeSet::from(
+            ["Food/Groceries", "Food/Restaurant", "Fun", "Transport/Fuel"].map(String::from),
+        );
+
+        assert_eq!(vec!["Food", "Fun"], complete_path(&categories, "f"));
+        assert_eq!(
+            vec!["Food/Restaurant"],
+            complete_path(&categories, "Food/Re")
+        );
+        assert!(complete_path(&categories, "Fun/").is_empty());
+    }
+}
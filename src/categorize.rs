@@ -0,0 +1,78 @@
+use std::{collections::HashMap, error::Error};
+
+use clap::Args;
+
+use crate::{
+    calc::Categorizer,
+    import::{self, Format, ImportArgs, ImportOptions},
+    Record,
+};
+
+/// Arguments for categorizing the records of statements up front.
+#[derive(Debug, Args)]
+pub struct CategorizeArgs {
+    #[arg(required = true)]
+    files: Vec<String>,
+    #[command(flatten)]
+    import: ImportArgs,
+}
+
+/// Categorize the records without a category in all the files, asking once for
+/// each payee, starting with the most frequent. The categories are written back
+/// to the CSV files and remembered in the lookup, also when the session is
+/// interrupted.
+pub fn run(args: &CategorizeArgs, categorizer: &mut Categorizer) -> Result<(), Box<dyn Error>> {
+    let opts: ImportOptions = (&args.import).try_into()?;
+
+    let mut statements = Vec::new();
+    for filename in args.files.iter() {
+        let statement = import::read_statement(filename, &opts)?;
+        let format = *statement.format();
+        let mut records = statement.into_records();
+        records
+            .iter_mut()
+            .for_each(|record| categorizer.normalize(record));
+        statements.push((filename, format, records));
+    }
+
+    let mut groups: HashMap<String, Vec<&mut Record>> = HashMap::new();
+    for record in statements.iter_mut().flat_map(|(_, _, records)| records) {
+        if record.category().is_none() && opts.includes(record) {
+            groups
+                .entry(record.get_payee().to_owned())
+                .or_default()
+                .push(record);
+        }
+    }
+    let mut groups: Vec<(String, Vec<&mut Record>)> = groups.into_iter().collect();
+    groups.sort_by(|(a, x), (b, y)| y.len().cmp(&x.len()).then_with(|| a.cmp(b)));
+    let mut groups: Vec<Vec<&mut Record>> = groups.into_iter().map(|(_, g)| g).collect();
+
+    println!(
+        "{} records without a category for {} payees",
+        groups.iter().map(Vec::len).sum::<usize>(),
+        groups.len()
+    );
+    // The answers given so far are written also when the session is interrupted
+    let mut result = categorizer.categorize_groups(&mut groups);
+    drop(groups);
+
+    for (filename, format, records) in statements.iter() {
+        for record in records.iter().filter(|r| opts.includes(r)) {
+            categorizer.remember(record);
+        }
+
+        match format {
+            Format::Csv => {
+                // Keep writing the other files if one of them fails
+                if let Err(e) = import::write_csv(filename, records, &opts) {
+                    eprintln!("Unable to write {filename}: {e}");
+                    result = result.and(Err(e));
+                }
+            }
+            _ => println!("Only the lookup is updated for {filename}, as it is not a CSV file"),
+        }
+    }
+
+    result
+}
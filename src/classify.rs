@@ -0,0 +1,184 @@
+use std::collections::{HashMap, HashSet};
+
+use derive_getters::Getters;
+
+/// A category suggested by the classifier, with a confidence between 0 and 1.
+#[derive(Debug, Clone, PartialEq, Getters)]
+pub struct Suggestion {
+    category: String,
+    confidence: f64,
+}
+
+/// Naive Bayes classifier over the words of the payees in the lookup.
+///
+/// A payee without any known word gets no suggestions at all. Words that were
+/// never seen count against every category, and the confidences leave room for
+/// the payee belonging to none of the known categories, so a single shared word
+/// does not make a suggestion certain.
+#[derive(Debug, Clone, Default)]
+pub struct Classifier {
+    /// Number of lookup entries per category.
+    documents: HashMap<String, usize>,
+    /// Number of times each word was seen per category.
+    words: HashMap<String, HashMap<String, usize>>,
+    /// Total number of words seen per category.
+    totals: HashMap<String, usize>,
+    /// Number of times each word was seen in any category.
+    vocabulary: HashMap<String, usize>,
+}
+
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() > 1)
+        .map(|t| t.to_lowercase())
+}
+
+impl Classifier {
+    /// Learn that the payee belongs to the category.
+    pub fn learn(&mut self, payee: &str, category: &str) {
+        if category.is_empty() {
+            return;
+        }
+
+        *self.documents.entry(category.to_owned()).or_default() += 1;
+        let words = self.words.entry(category.to_owned()).or_default();
+        for token in tokenize(payee) {
+            *words.entry(token.clone()).or_default() += 1;
+            *self.totals.entry(category.to_owned()).or_default() += 1;
+            *self.vocabulary.entry(token).or_default() += 1;
+        }
+    }
+
+    /// Forget that the payee belongs to the category, e.g. when its lookup entry
+    /// is overwritten. Does nothing if it was not learned.
+    pub fn unlearn(&mut self, payee: &str, category: &str) {
+        fn decrement(map: &mut HashMap<String, usize>, key: &str) {
+            if let Some(n) = map.get_mut(key) {
+                *n -= 1;
+                if *n == 0 {
+                    map.remove(key);
+                }
+            }
+        }
+
+        let Some(words) = self.words.get_mut(category) else {
+            return;
+        };
+        for token in tokenize(payee) {
+            if words.contains_key(&token) {
+                decrement(words, &token);
+                decrement(&mut self.totals, category);
+                decrement(&mut self.vocabulary, &token);
+            }
+        }
+        decrement(&mut self.documents, category);
+        if !self.documents.contains_key(category) {
+            self.words.remove(category);
+            self.totals.remove(category);
+        }
+    }
+
+    /// Get the most likely categories for the payee, best first.
+    pub fn suggest(&self, payee: &str, count: usize) -> Vec<Suggestion> {
+        let tokens: Vec<String> = tokenize(payee).collect();
+        if !tokens.iter().any(|t| self.vocabulary.contains_key(t)) {
+            return Vec::new();
+        }
+
+        let documents: usize = self.documents.values().sum();
+        // Unseen words are added to the vocabulary, so they are smoothed like the others
+        let unseen = tokens
+            .iter()
+            .filter(|t| !self.vocabulary.contains_key(*t))
+            .collect::<HashSet<_>>()
+            .len();
+        let vocabulary = (self.vocabulary.len() + unseen) as f64;
+        let mut scores: Vec<(&String, f64)> = self
+            .documents
+            .iter()
+            .map(|(category, n)| {
+                let words = &self.words[category];
+                let total = self.totals.get(category).copied().unwrap_or_default() as f64;
+                let likelihood: f64 = tokens
+                    .iter()
+                    .map(|t| {
+                        let seen = words.get(t).copied().unwrap_or_default() as f64;
+                        ((seen + 1.0) / (total + vocabulary)).ln()
+                    })
+                    .sum();
+                (
+                    category,
+                    (*n as f64 / (documents + 1) as f64).ln() + likelihood,
+                )
+            })
+            .collect();
+        // A payee from none of the categories, as likely as one extra entry with
+        // all the words equally likely.
+        let other = String::new();
+        scores.push((
+            &other,
+            (1.0 / (documents + 1) as f64).ln() - tokens.len() as f64 * vocabulary.ln(),
+        ));
+
+        // Turn the log probabilities into confidences summing to one.
+        let max = scores
+            .iter()
+            .map(|(_, s)| *s)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let sum: f64 = scores.iter().map(|(_, s)| (s - max).exp()).sum();
+
+        let mut suggestions: Vec<Suggestion> = scores
+            .into_iter()
+            .filter(|(category, _)| !category.is_empty())
+            .map(|(category, score)| Suggestion {
+                category: category.to_owned(),
+                confidence: (score - max).exp() / sum,
+            })
+            .collect();
+        suggestions.sort_by(|a, b| {
+            b.confidence
+                .total_cmp(&a.confidence)
+                .then_with(|| a.category.cmp(&b.category))
+        });
+        suggestions.truncate(count);
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn suggest_categories_from_known_words() {
+        let mut classifier = Classifier::default();
+        classifier.learn("tesco stores", "Food/Groceries");
+        classifier.learn("tesco express", "Food/Groceries");
+        classifier.learn("sainsburys", "Food/Groceries");
+        classifier.learn("shell petrol station", "Transport/Fuel");
+        classifier.learn("bp petrol", "Transport/Fuel");
+
+        let suggestions = classifier.suggest("tesco metro", 3);
+        assert_eq!(2, suggestions.len());
+        assert_eq!("Food/Groceries", suggestions[0].category());
+        // "metro" was never seen, which leaves room for an unknown category
+        assert!(*suggestions[0].confidence() > 0.6);
+
+        let suggestions = classifier.suggest("esso petrol", 1);
+        assert_eq!("Transport/Fuel", suggestions[0].category());
+
+        assert!(classifier.suggest("unknown payee", 3).is_empty());
+
+        // A single shared word with a single category is not certain
+        let mut classifier = Classifier::default();
+        classifier.learn("amazon marketplace", "Home");
+        let confidence = *classifier.suggest("amazon prime video", 1)[0].confidence();
+        assert!(confidence < 0.9, "{confidence}");
+
+        classifier.learn("amazon marketplace", "Gifts");
+        classifier.unlearn("amazon marketplace", "Home");
+        let suggestions = classifier.suggest("amazon marketplace", 3);
+        assert_eq!(1, suggestions.len());
+        assert_eq!("Gifts", suggestions[0].category());
+    }
+}
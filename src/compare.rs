@@ -1,18 +1,28 @@
+use std::error::Error;
+
 use clap::Args;
 
 use crate::{
-    tree::compare_tree::{CompareOptions, CompareTree},
-    utils::Lookup,
+    calc::Categorizer,
+    import::{ImportArgs, ImportOptions},
+    period::PeriodArgs,
+    tree::{
+        compare_tree::{CompareOptions, CompareTree},
+        TotalsArgs, TotalsOptions,
+    },
     Tree,
 };
 
-pub fn run(args: &CompareArgs, lookup: &mut Lookup) {
-    let trees = args.get_trees(lookup);
+pub fn run(args: &CompareArgs, categorizer: &mut Categorizer) -> Result<(), Box<dyn Error>> {
+    let trees = args.get_trees(categorizer)?;
+    categorizer.check()?;
     let compare_tree = CompareTree::new(&trees, args.into());
     println!("{compare_tree}");
+
+    Ok(())
 }
 
-/// Arguments for comparing multiple of files.
+/// Arguments for comparing multiple of files, or the periods of a statement.
 #[derive(Debug, Args)]
 pub struct CompareArgs {
     files: Vec<String>,
@@ -22,14 +32,36 @@ pub struct CompareArgs {
     hide_ignored_categories: bool,
     #[arg(short, long, default_value = "12")]
     number_of_colunms: usize,
+    /// Also show what was spent and saved in each account.
+    #[arg(long)]
+    by_account: bool,
+    #[command(flatten)]
+    periods: PeriodArgs,
+    /// Do not write the categories back to the files. The lookup is still updated.
+    #[arg(long)]
+    dry_run: bool,
+    #[command(flatten)]
+    totals: TotalsArgs,
+    #[command(flatten)]
+    import: ImportArgs,
 }
 
 impl CompareArgs {
-    pub fn get_trees(&self, lookup: &mut Lookup) -> Vec<Tree> {
-        self.files
-            .iter()
-            .map(|f| Tree::load_from_file(f, lookup).unwrap())
-            .collect()
+    pub fn get_trees(&self, categorizer: &mut Categorizer) -> Result<Vec<Tree>, Box<dyn Error>> {
+        let opts: ImportOptions = (&self.import).try_into()?;
+        let totals: TotalsOptions = (&self.totals).try_into()?;
+        if let Some(granularity) = self.periods.granularity() {
+            return Tree::load_by_period(
+                &self.files,
+                categorizer,
+                &opts,
+                &totals,
+                !self.dry_run,
+                granularity,
+                (&self.periods).into(),
+            );
+        }
+        Tree::load_from_files(&self.files, categorizer, &opts, &totals, !self.dry_run)
     }
 }
 
@@ -39,6 +71,7 @@ impl From<&CompareArgs> for CompareOptions {
             value.ignored_categories.as_str().into(),
             value.hide_ignored_categories,
             value.number_of_colunms,
+            value.by_account,
         )
     }
 }
@@ -0,0 +1,181 @@
+use std::error::Error;
+
+use chrono::{Days, Months, NaiveDate};
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Format used when writing dates back to a CSV file.
+pub const OUTPUT_FORMAT: &str = "%d/%m/%Y";
+
+/// Formats tried when no formats have been configured.
+pub const DEFAULT_FORMATS: [&str; 2] = ["%d/%m/%Y", "%Y-%m-%d"];
+
+/// Ordered list of `chrono` format strings used to parse transaction dates.
+/// The first format that successfully parses a value is used.
+#[derive(Debug, Clone)]
+pub struct DateFormats(Vec<String>);
+
+impl DateFormats {
+    pub fn new(formats: Vec<String>) -> Self {
+        if formats.is_empty() {
+            Self::default()
+        } else {
+            Self(formats)
+        }
+    }
+
+    pub fn parse(&self, value: &str) -> Result<NaiveDate, Box<dyn Error>> {
+        let value = value.trim();
+        self.0
+            .iter()
+            .find_map(|f| NaiveDate::parse_from_str(value, f).ok())
+            .ok_or_else(|| {
+                format!(
+                    "Invalid date '{value}', expected one of the formats: {}",
+                    self.0.join(", ")
+                )
+                .into()
+            })
+    }
+}
+
+impl Default for DateFormats {
+    fn default() -> Self {
+        Self(DEFAULT_FORMATS.iter().map(|f| f.to_string()).collect())
+    }
+}
+
+/// Inclusive range of dates that records are limited to. Either end can be open.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DateRange {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub fn new(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Self {
+        Self { from, to }
+    }
+
+    pub fn contains(&self, date: &NaiveDate) -> bool {
+        self.from.map(|from| from <= *date).unwrap_or(true)
+            && self.to.map(|to| *date <= to).unwrap_or(true)
+    }
+}
+
+/// Get the start of a period ending on `end`, given as a number and a unit of
+/// days (`d`), weeks (`w`), months (`m`) or years (`y`), e.g. `90d` or `3m`.
+/// Both ends are included, so `90d` is `end` and the 89 days before it.
+pub fn parse_last(value: &str, end: NaiveDate) -> Result<NaiveDate, Box<dyn Error>> {
+    let invalid = || format!("Invalid period '{value}', expected e.g. 90d, 2w, 3m or 1y");
+    let value = value.trim();
+    let unit = value.chars().last().ok_or_else(invalid)?;
+    let n: u32 = value[..value.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| invalid())?;
+    // An empty period would leave out every record
+    if n == 0 {
+        return Err(invalid().into());
+    }
+
+    match unit {
+        'd' => end.checked_sub_days(Days::new(n.into())),
+        'w' => end.checked_sub_days(Days::new(7 * u64::from(n))),
+        'm' => end.checked_sub_months(Months::new(n)),
+        'y' => end.checked_sub_months(Months::new(12 * n)),
+        _ => return Err(invalid().into()),
+    }
+    .and_then(|start| start.succ_opt())
+    .ok_or_else(|| invalid().into())
+}
+
+/// Serde helpers to read and write a `NaiveDate` in a CSV file.
+pub mod csv_format {
+    use super::*;
+
+    pub fn serialize<S>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(&date.format(OUTPUT_FORMAT))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: &str = Deserialize::deserialize(deserializer)?;
+        DateFormats::default()
+            .parse(s)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Serde helper to read an optional `NaiveDate` in one of the default formats.
+pub mod option_format {
+    use super::*;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: Option<String> = Deserialize::deserialize(deserializer)?;
+        s.map(|s| DateFormats::default().parse(&s))
+            .transpose()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_with_first_matching_format() {
+        let formats = DateFormats::default();
+
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2023, 1, 31).unwrap(),
+            formats.parse("31/01/2023").unwrap()
+        );
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2023, 2, 1).unwrap(),
+            formats.parse("2023-02-01").unwrap()
+        );
+        assert!(formats.parse("Feb 1st").is_err());
+    }
+
+    #[test]
+    fn parse_relative_periods() {
+        let end = NaiveDate::from_ymd_opt(2023, 5, 31).unwrap();
+
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2023, 3, 3).unwrap(),
+            parse_last("90d", end).unwrap()
+        );
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2023, 3, 1).unwrap(),
+            parse_last("3m", end).unwrap()
+        );
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2022, 6, 1).unwrap(),
+            parse_last("1y", end).unwrap()
+        );
+        assert!(parse_last("3x", end).is_err());
+        assert!(parse_last("m", end).is_err());
+        assert!(parse_last("0d", end).is_err());
+
+        let range = DateRange::new(Some(parse_last("2w", end).unwrap()), None);
+        assert!(range.contains(&NaiveDate::from_ymd_opt(2023, 5, 18).unwrap()));
+        assert!(!range.contains(&NaiveDate::from_ymd_opt(2023, 5, 17).unwrap()));
+    }
+
+    #[test]
+    fn parse_us_short_format() {
+        let formats = DateFormats::new(vec!["%m/%d/%y".to_string()]);
+
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2023, 2, 1).unwrap(),
+            formats.parse("02/01/23").unwrap()
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::{error::Error, fs};
+
+use chrono::NaiveDate;
+use derive_getters::Getters;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::Record;
+
+/// Identifies a single transaction, either by its id or by its date, description
+/// and amount. The description ignores case.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Transaction {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default, with = "crate::date::option_format")]
+    date: Option<NaiveDate>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    amount: Option<Decimal>,
+}
+
+impl Transaction {
+    pub fn matches(&self, record: &Record) -> bool {
+        if let Some(id) = &self.id {
+            return *id == record.id();
+        }
+
+        match (&self.date, &self.description, &self.amount) {
+            (Some(date), Some(description), Some(amount)) => {
+                date == record.date()
+                    && description
+                        .trim()
+                        .eq_ignore_ascii_case(record.description())
+                    && *amount == record.get_original_amount()
+            }
+            _ => false,
+        }
+    }
+
+    /// Check that the transaction can be matched, naming the file it comes from otherwise.
+    pub fn validate(&self, filename: &str) -> Result<(), Box<dyn Error>> {
+        if self.id.is_none()
+            && (self.date.is_none() || self.description.is_none() || self.amount.is_none())
+        {
+            return Err(format!(
+                "Transactions in {filename} need an id, or a date, description and amount: {self:?}"
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// A single transaction that is kept out of the totals.
+#[derive(Debug, Clone, Deserialize, Getters)]
+pub struct Exclusion {
+    #[serde(flatten)]
+    transaction: Transaction,
+    /// Why the transaction is excluded, shown in the reports.
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// Transactions kept out of the totals.
+#[derive(Debug, Clone, Default)]
+pub struct Exclusions(Vec<Exclusion>);
+
+impl Exclusions {
+    /// Load the exclusions from a JSON file. If the file does not exist, nothing is excluded.
+    pub fn new(filename: impl AsRef<str>) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(filename.as_ref()) {
+            Ok(content) => {
+                let exclusions: Vec<Exclusion> = serde_json::from_str(&content)
+                    .map_err(|e| format!("Invalid exclusions in {}: {e}", filename.as_ref()))?;
+                for exclusion in exclusions.iter() {
+                    exclusion.transaction.validate(filename.as_ref())?;
+                }
+                Ok(Self(exclusions))
+            }
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Find the exclusion matching the record, if any.
+    pub fn find(&self, record: &Record) -> Option<&Exclusion> {
+        self.0.iter().find(|e| e.transaction.matches(record))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn match_on_id_or_transaction_details() {
+        let deposit = Record::new(
+            NaiveDate::from_ymd_opt(2023, 3, 1).unwrap(),
+            "SOLICITORS LTD".to_string(),
+            Some(Decimal::new(25000, 0)),
+            None,
+            None,
+        );
+        let other = Record::new(
+            NaiveDate::from_ymd_opt(2023, 3, 2).unwrap(),
+            "SOLICITORS LTD".to_string(),
+            Some(Decimal::new(25000, 0)),
+            None,
+            None,
+        );
+        let exclusions = Exclusions(
+            serde_json::from_str(&format!(
+                r#"[
+                    {{ "date": "01/03/2023", "description": "solicitors ltd", "amount": -25000, "reason": "House deposit" }},
+                    {{ "id": "{}" }}
+                ]"#,
+                other.id()
+            ))
+            .unwrap(),
+        );
+
+        assert_eq!(
+            Some("House deposit"),
+            exclusions
+                .find(&deposit)
+                .and_then(|e| e.reason().as_deref())
+        );
+        assert!(exclusions.find(&other).is_some());
+        assert_eq!(16, other.id().len());
+        assert_ne!(deposit.id(), other.id());
+
+        // Identical transactions are told apart, and ids from the bank are used
+        let mut second = other.clone();
+        second.set_occurrence(1);
+        assert!(exclusions.find(&second).is_none());
+        let mut banked = other.clone();
+        banked.set_bank_id(Some("A1".to_string()));
+        assert_ne!(other.id(), banked.id());
+    }
+}
@@ -0,0 +1,72 @@
+use std::error::Error;
+
+use clap::Args;
+use colored::Colorize;
+
+use crate::{
+    calc::{Categorizer, CategorySource},
+    exclusions::Exclusions,
+    import::{self, ImportArgs, ImportOptions},
+    utils::format_with_color,
+};
+
+/// Arguments for explaining how the records in a file are categorized.
+#[derive(Debug, Args)]
+pub struct ExplainArgs {
+    filename: String,
+    /// Only show the records without a category in the file.
+    #[arg(short, long)]
+    uncategorized: bool,
+    /// JSON file with the transactions to keep out of the totals, which are marked.
+    #[arg(long, default_value = "exclusions.json")]
+    exclusions: String,
+    #[command(flatten)]
+    import: ImportArgs,
+}
+
+/// Show the category each record gets, and whether it comes from the file,
+/// a rule or the lookup. Nothing is written and the user is never asked.
+pub fn run(args: &ExplainArgs, categorizer: &Categorizer) -> Result<(), Box<dyn Error>> {
+    let opts: ImportOptions = (&args.import).try_into()?;
+    let exclusions = Exclusions::new(&args.exclusions)?;
+
+    for mut record in import::read_records(&args.filename, &opts)? {
+        categorizer.normalize(&mut record);
+        if !opts.includes(&record) || (args.uncategorized && record.category().is_some()) {
+            continue;
+        }
+
+        let (category, source) = categorizer.explain(&record);
+        let source = match source {
+            CategorySource::File => "file".to_string(),
+            CategorySource::Rule(index, rule) => match rule.name() {
+                Some(name) => format!("rule #{} ({name})", index + 1),
+                None => format!("rule #{}", index + 1),
+            },
+            CategorySource::Lookup => "lookup".to_string(),
+            CategorySource::Classifier(confidence) => {
+                format!("classifier ({:.0}%)", confidence * 100.0)
+                    .yellow()
+                    .to_string()
+            }
+            CategorySource::Missing => "missing".red().to_string(),
+        };
+
+        let source = match exclusions.find(&record) {
+            Some(_) => format!("{source} {}", "excluded".yellow()),
+            None => source,
+        };
+
+        println!(
+            "{} {} {:<40}{:<30}{:>10}  {:<30}{source}",
+            record.id().dimmed(),
+            record.date().to_string().cyan(),
+            record.description().green(),
+            record.get_payee(),
+            format_with_color(record.get_amount()),
+            category.unwrap_or_default(),
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    str::FromStr,
+};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::{date::DateFormats, Record};
+
+/// Table of daily exchange rates, read from a CSV file with the columns
+/// `Date`, `From`, `To` and `Rate`, where `Rate` is the value of one unit of
+/// `From` in `To`.
+///
+/// Rates are only needed for the days with transactions. If there is no rate for
+/// the exact day, the most recent earlier rate is used, e.g. to cover weekends.
+#[derive(Debug, Clone, Default)]
+pub struct FxRates(HashMap<(String, String), BTreeMap<NaiveDate, Decimal>>);
+
+impl FxRates {
+    /// Load the rates from a file. If the file does not exist, the table is empty.
+    pub fn new(filename: impl AsRef<str>) -> Result<Self, Box<dyn Error>> {
+        let mut reader = match csv::Reader::from_path(filename.as_ref()) {
+            Ok(reader) => reader,
+            Err(_) => return Ok(Self::default()),
+        };
+
+        let date_formats = DateFormats::default();
+        let mut rates = Self::default();
+        for (row, result) in reader.records().enumerate() {
+            let line = result?;
+            let field = |i: usize| line.get(i).unwrap_or_default().trim();
+            let error = |e: String| format!("{e} on row {} in {}", row + 1, filename.as_ref());
+
+            let date = date_formats
+                .parse(field(0))
+                .map_err(|e| error(e.to_string()))?;
+            let rate = Decimal::from_str(field(3)).map_err(|e| error(e.to_string()))?;
+            rates.insert(date, field(1), field(2), rate);
+        }
+
+        Ok(rates)
+    }
+
+    pub fn insert(&mut self, date: NaiveDate, from: &str, to: &str, rate: Decimal) {
+        self.0
+            .entry((from.to_uppercase(), to.to_uppercase()))
+            .or_default()
+            .insert(date, rate);
+    }
+
+    /// Get the rate to convert from one currency to another on a given day.
+    /// The inverse of the opposite rate is used if there is no direct rate.
+    pub fn get(&self, date: NaiveDate, from: &str, to: &str) -> Option<Decimal> {
+        let (from, to) = (from.to_uppercase(), to.to_uppercase());
+        if from == to {
+            return Some(Decimal::ONE);
+        }
+
+        let latest = |key: (String, String)| {
+            self.0
+                .get(&key)
+                .and_then(|rates| rates.range(..=date).next_back())
+                .map(|(_, rate)| *rate)
+        };
+
+        latest((from.clone(), to.clone())).or_else(|| {
+            latest((to, from))
+                .filter(|rate| !rate.is_zero())
+                .map(|rate| Decimal::ONE / rate)
+        })
+    }
+
+    /// Set the rate on the record to convert it into the reporting currency.
+    /// Records without a currency are assumed to already be in the reporting currency.
+    pub fn convert(&self, record: &mut Record, currency: &str) -> Result<(), Box<dyn Error>> {
+        if let Some(from) = record.currency().clone() {
+            let rate = self.get(*record.date(), &from, currency).ok_or_else(|| {
+                format!(
+                    "No exchange rate from {from} to {currency} on or before {}",
+                    record.date()
+                )
+            })?;
+            record.set_rate(rate);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn get_latest_and_inverse_rate() {
+        let date = |d: u32| NaiveDate::from_ymd_opt(2023, 1, d).unwrap();
+        let mut rates = FxRates::default();
+        rates.insert(date(2), "EUR", "GBP", Decimal::new(88, 2));
+        rates.insert(date(5), "EUR", "GBP", Decimal::new(90, 2));
+
+        assert_eq!(None, rates.get(date(1), "EUR", "GBP"));
+        assert_eq!(Some(Decimal::new(88, 2)), rates.get(date(4), "eur", "GBP"));
+        assert_eq!(Some(Decimal::new(90, 2)), rates.get(date(5), "EUR", "GBP"));
+        assert_eq!(
+            Some(Decimal::ONE / Decimal::new(90, 2)),
+            rates.get(date(6), "GBP", "EUR")
+        );
+        assert_eq!(Some(Decimal::ONE), rates.get(date(1), "DKK", "DKK"));
+    }
+}
@@ -0,0 +1,321 @@
+pub mod camt;
+pub mod mt940;
+pub mod ofx;
+pub mod qif;
+pub mod schema;
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    fs,
+};
+
+use chrono::Local;
+use clap::Args;
+use derive_getters::Getters;
+use derive_new::new;
+
+use self::schema::{Schema, Schemas};
+use crate::{
+    date::{self, DateFormats, DateRange},
+    fx::FxRates,
+    Record,
+};
+
+/// Arguments controlling how statement files are read.
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// File with the bank CSV schema profiles.
+    #[arg(long, default_value = "schemas.json")]
+    schemas: String,
+    /// Name of the schema profile to use. Detected from the headers if omitted.
+    #[arg(long)]
+    schema: Option<String>,
+    /// Format of the transaction dates, e.g. `%m/%d/%y`. Can be repeated, in which
+    /// case the first format that matches is used.
+    #[arg(long = "date-format")]
+    date_formats: Vec<String>,
+    /// Currency to report amounts in. Records in other currencies are converted
+    /// using the exchange rates.
+    #[arg(long)]
+    currency: Option<String>,
+    /// CSV file with daily exchange rates.
+    #[arg(long, default_value = "fx_rates.csv")]
+    fx_rates: String,
+    /// Only include records from this account, given either by sort code and
+    /// account number or just the account number. Can be repeated.
+    #[arg(long = "account")]
+    accounts: Vec<String>,
+    /// Only include records on or after this date.
+    #[arg(long, conflicts_with = "last")]
+    from: Option<String>,
+    /// Only include records on or before this date. With `--last`, the period
+    /// ends on this date instead of today.
+    #[arg(long)]
+    to: Option<String>,
+    /// Only include records from a period ending today, or on `--to` if given,
+    /// e.g. `90d`, `2w`, `3m` or `1y`. Both ends are included, so `90d` is 90 days
+    /// including today. Cannot be used with `--from`.
+    #[arg(long)]
+    last: Option<String>,
+}
+
+#[derive(Debug, Clone, new, Getters)]
+pub struct ImportOptions {
+    schemas: Schemas,
+    profile: Option<String>,
+    date_formats: DateFormats,
+    currency: Option<String>,
+    fx_rates: FxRates,
+    accounts: Vec<String>,
+    dates: DateRange,
+}
+
+impl ImportOptions {
+    /// Check whether a record should be included, based on the account and date filters.
+    pub fn includes(&self, record: &Record) -> bool {
+        (self.accounts.is_empty() || self.accounts.iter().any(|a| record.is_in_account(a)))
+            && self.dates.contains(record.date())
+    }
+}
+
+impl TryFrom<&ImportArgs> for ImportOptions {
+    type Error = Box<dyn Error>;
+
+    fn try_from(value: &ImportArgs) -> Result<Self, Self::Error> {
+        let date_formats = DateFormats::new(value.date_formats.clone());
+        let to = value
+            .to
+            .as_deref()
+            .map(|d| date_formats.parse(d))
+            .transpose()?;
+        let from = match &value.last {
+            Some(last) => Some(date::parse_last(
+                last,
+                to.unwrap_or_else(|| Local::now().date_naive()),
+            )?),
+            None => value
+                .from
+                .as_deref()
+                .map(|d| date_formats.parse(d))
+                .transpose()?,
+        };
+
+        Ok(ImportOptions::new(
+            Schemas::new(&value.schemas)?,
+            value.schema.clone(),
+            date_formats,
+            value.currency.clone(),
+            // The rates are only needed, and checked, when converting
+            match value.currency {
+                Some(_) => FxRates::new(&value.fx_rates)?,
+                None => FxRates::default(),
+            },
+            value.accounts.clone(),
+            DateRange::new(from, to),
+        ))
+    }
+}
+
+/// The file formats statements can be imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Ofx,
+    Camt,
+    Mt940,
+    Qif,
+}
+
+impl Format {
+    /// Detect the format from the content of a file. Anything that isn't
+    /// recognized as another format is assumed to be CSV.
+    pub fn detect(content: &str) -> Self {
+        if ofx::is_ofx(content) {
+            Format::Ofx
+        } else if camt::is_camt(content) {
+            Format::Camt
+        } else if mt940::is_mt940(content) {
+            Format::Mt940
+        } else if qif::is_qif(content) {
+            Format::Qif
+        } else {
+            Format::Csv
+        }
+    }
+}
+
+/// The records read from a single statement file.
+#[derive(Debug, Getters)]
+pub struct Statement {
+    format: Format,
+    records: Vec<Record>,
+}
+
+impl Statement {
+    pub fn into_records(self) -> Vec<Record> {
+        self.records
+    }
+}
+
+/// Read all the records in a statement file, normalized to `Record`s.
+/// For CSV files the schema profile is either the one named in the options,
+/// or the first profile matching the headers in the file.
+pub fn read_statement(
+    filename: impl AsRef<str>,
+    opts: &ImportOptions,
+) -> Result<Statement, Box<dyn Error>> {
+    let filename = filename.as_ref();
+    let content = fs::read_to_string(filename)?;
+
+    let format = Format::detect(&content);
+    let mut records = match format {
+        Format::Csv => read_csv(filename, &content, opts)?,
+        Format::Ofx => ofx::read(&content)?,
+        Format::Camt => camt::read(&content)?,
+        Format::Mt940 => mt940::read(&content)?,
+        Format::Qif => qif::read(&content, opts.date_formats())?,
+    };
+
+    // Tell identical transactions apart, e.g. two coffees on the same day
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for record in records.iter_mut() {
+        let occurrence = seen.entry(record.id()).or_default();
+        record.set_occurrence(*occurrence);
+        *occurrence += 1;
+    }
+
+    if let Some(currency) = opts.currency() {
+        for record in records.iter_mut() {
+            opts.fx_rates().convert(record, currency)?;
+        }
+    }
+
+    Ok(Statement { format, records })
+}
+
+/// Check that the records can be added up, which needs a reporting currency
+/// when they are in more than one currency.
+pub fn check_currencies<'a>(
+    records: impl IntoIterator<Item = &'a Record>,
+    opts: &ImportOptions,
+) -> Result<(), Box<dyn Error>> {
+    if opts.currency().is_some() {
+        return Ok(());
+    }
+    // Records without a currency are in the one of their statement, which may differ
+    let currencies: BTreeSet<&str> = records
+        .into_iter()
+        .map(|r| r.currency().as_deref().unwrap_or("unknown"))
+        .collect();
+    if currencies.len() > 1 {
+        return Err(format!(
+            "The records are in several currencies ({}), use --currency to convert them to one",
+            currencies.into_iter().collect::<Vec<_>>().join(", ")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Read all the records in a statement file. See `read_statement`.
+pub fn read_records(
+    filename: impl AsRef<str>,
+    opts: &ImportOptions,
+) -> Result<Vec<Record>, Box<dyn Error>> {
+    read_statement(filename, opts).map(Statement::into_records)
+}
+
+/// Write the categories and tags of the records back to the CSV file they were
+/// read from, keeping all the other columns as they are. If anything changed, a
+/// timestamped backup of the original file is made, and the file is replaced
+/// through a temporary file so it is left intact if writing fails. Returns
+/// whether the file was written.
+pub fn write_csv(
+    filename: impl AsRef<str>,
+    records: &[Record],
+    opts: &ImportOptions,
+) -> Result<bool, Box<dyn Error>> {
+    let filename = filename.as_ref();
+    let content = fs::read_to_string(filename)?;
+
+    let Some(content) = schema(filename, &content, opts)?.write(&content, records)? else {
+        return Ok(false);
+    };
+
+    let backup = format!("{filename}.{}.bak", Local::now().format("%Y%m%d%H%M%S"));
+    fs::copy(filename, backup)?;
+    let tmp = filename.to_owned() + ".tmp";
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, filename)?;
+
+    Ok(true)
+}
+
+fn schema<'a>(
+    filename: &str,
+    content: &str,
+    opts: &'a ImportOptions,
+) -> Result<&'a Schema, Box<dyn Error>> {
+    match opts.profile() {
+        Some(name) => opts
+            .schemas()
+            .get(name)
+            .ok_or_else(|| format!("Unknown schema profile: {name}").into()),
+        None => opts
+            .schemas()
+            .detect(content)
+            .ok_or_else(|| format!("Unable to detect the format of {filename}").into()),
+    }
+}
+
+fn read_csv(
+    filename: &str,
+    content: &str,
+    opts: &ImportOptions,
+) -> Result<Vec<Record>, Box<dyn Error>> {
+    schema(filename, content, opts)?.read(content, opts.date_formats())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use super::*;
+
+    fn record(currency: Option<&str>) -> Record {
+        let mut record = Record::new(
+            NaiveDate::from_ymd_opt(2023, 1, 31).unwrap(),
+            "TESCO".to_string(),
+            Some(Decimal::ONE),
+            None,
+            None,
+        );
+        record.set_currency(currency.map(|c| c.to_string()));
+        record
+    }
+
+    #[test]
+    fn records_without_a_currency_are_not_mixed_with_others() {
+        let opts = |currency: Option<&str>| {
+            ImportOptions::new(
+                Schemas::default(),
+                None,
+                DateFormats::default(),
+                currency.map(|c| c.to_string()),
+                FxRates::default(),
+                Vec::new(),
+                DateRange::default(),
+            )
+        };
+        let same = [record(Some("EUR")), record(Some("EUR"))];
+        let mixed = [record(None), record(Some("EUR"))];
+
+        assert!(check_currencies(&same, &opts(None)).is_ok());
+        assert!(check_currencies(&[record(None)], &opts(None)).is_ok());
+        let error = check_currencies(&mixed, &opts(None)).unwrap_err();
+        assert!(error.to_string().contains("EUR, unknown"));
+        assert!(check_currencies(&mixed, &opts(Some("GBP"))).is_ok());
+    }
+}
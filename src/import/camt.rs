@@ -0,0 +1,233 @@
+use std::{error::Error, str::FromStr};
+
+use chrono::NaiveDate;
+use roxmltree::{Document, Node};
+use rust_decimal::Decimal;
+
+use crate::Record;
+
+const NAMESPACE: &str = "urn:iso:std:iso:20022:tech:xsd:camt.05";
+
+/// Check whether the content looks like an ISO 20022 camt.052 or camt.053 document.
+pub fn is_camt(content: &str) -> bool {
+    content.contains(NAMESPACE)
+        || content.contains("<BkToCstmrStmt>")
+        || content.contains("<BkToCstmrAcctRpt>")
+}
+
+/// Read the transactions from a camt.053 statement or camt.052 account report.
+///
+/// Each transaction detail (`TxDtls`) within an entry (`Ntry`) becomes a record.
+/// Entries without any details, such as fees, become a single record.
+pub fn read(content: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+    let document = Document::parse(content)?;
+    let mut records = Vec::new();
+
+    for entry in document.descendants().filter(|n| n.has_tag_name("Ntry")) {
+        let reference = text(entry, &["NtryRef"]).unwrap_or_default();
+        let date = text(entry, &["BookgDt", "Dt"])
+            .or_else(|| text(entry, &["BookgDt", "DtTm"]))
+            .or_else(|| text(entry, &["ValDt", "Dt"]))
+            .ok_or_else(|| format!("Entry {reference} is missing a booking date"))?;
+        let date = date
+            .get(..10)
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            .ok_or_else(|| format!("Entry {reference} has an invalid booking date '{date}'"))?;
+
+        let details: Vec<Node> = entry
+            .children()
+            .filter(|n| n.has_tag_name("NtryDtls"))
+            .flat_map(|n| n.children().filter(|n| n.has_tag_name("TxDtls")))
+            .collect();
+
+        if details.len() <= 1 {
+            let detail = details.first().copied();
+            let amount = detail
+                .and_then(transaction_amount)
+                .or_else(|| find(entry, &["Amt"]));
+            let mut record = to_record(entry, detail, date, amount, &reference)?;
+            record.set_bank_id(
+                detail
+                    .and_then(bank_reference)
+                    .or_else(|| text(entry, &["AcctSvcrRef"])),
+            );
+            records.push(record);
+        } else {
+            // The reference of the entry is shared by its transactions
+            for detail in details {
+                let amount = transaction_amount(detail);
+                let mut record = to_record(entry, Some(detail), date, amount, &reference)?;
+                record.set_bank_id(bank_reference(detail));
+                records.push(record);
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+fn to_record(
+    entry: Node,
+    detail: Option<Node>,
+    date: NaiveDate,
+    amount: Option<Node>,
+    reference: &str,
+) -> Result<Record, Box<dyn Error>> {
+    let amount = amount.ok_or_else(|| format!("Entry {reference} is missing an amount"))?;
+    let currency = amount.attribute("Ccy").map(|c| c.to_string());
+    let amount = amount.text().unwrap_or_default().trim();
+    let amount = Decimal::from_str(amount)
+        .map_err(|e| format!("Entry {reference} has an invalid amount '{amount}': {e}"))?;
+
+    let indicator = detail
+        .and_then(|d| text(d, &["CdtDbtInd"]))
+        .or_else(|| text(entry, &["CdtDbtInd"]))
+        .ok_or_else(|| format!("Entry {reference} is missing CdtDbtInd"))?;
+    let is_debit = match indicator.as_str() {
+        "DBIT" => true,
+        "CRDT" => false,
+        _ => {
+            return Err(format!("Entry {reference} has an invalid CdtDbtInd '{indicator}'").into())
+        }
+    };
+
+    let description = detail
+        .map(|d| description(d, is_debit))
+        .filter(|d| !d.is_empty())
+        .or_else(|| text(entry, &["AddtlNtryInf"]))
+        .unwrap_or_default();
+
+    let mut record = if is_debit {
+        Record::new(date, description, Some(amount.abs()), None, None)
+    } else {
+        Record::new(date, description, None, Some(amount.abs()), None)
+    };
+    record.set_currency(currency);
+    record.set_account_number(entry.parent().and_then(|statement| {
+        text(statement, &["Acct", "Id", "IBAN"])
+            .or_else(|| text(statement, &["Acct", "Id", "Othr", "Id"]))
+    }));
+    Ok(record)
+}
+
+/// The reference the bank gave to the transaction.
+fn bank_reference(detail: Node) -> Option<String> {
+    text(detail, &["Refs", "AcctSvcrRef"])
+}
+
+fn transaction_amount<'a, 'input>(detail: Node<'a, 'input>) -> Option<Node<'a, 'input>> {
+    find(detail, &["Amt"]).or_else(|| find(detail, &["AmtDtls", "TxAmt", "Amt"]))
+}
+
+/// The name of the counterparty followed by the remittance information.
+/// For debits the counterparty is the creditor, and for credits it is the debtor.
+fn description(detail: Node, is_debit: bool) -> String {
+    let party = if is_debit { "Cdtr" } else { "Dbtr" };
+    let name = text(detail, &["RltdPties", party, "Nm"])
+        .or_else(|| text(detail, &["RltdPties", party, "Pty", "Nm"]));
+
+    let remittance = find(detail, &["RmtInf"])
+        .map(|r| {
+            r.children()
+                .filter(|n| n.has_tag_name("Ustrd"))
+                .filter_map(|n| n.text())
+                .map(|t| t.trim())
+                .collect::<Vec<&str>>()
+                .join(" ")
+        })
+        .filter(|r| !r.is_empty())
+        .or_else(|| text(detail, &["RmtInf", "Strd", "CdtrRefInf", "Ref"]))
+        .or_else(|| text(detail, &["AddtlTxInf"]));
+
+    [name, remittance]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<String>>()
+        .join(" - ")
+}
+
+/// Find the first element following the path of local tag names.
+fn find<'a, 'input>(node: Node<'a, 'input>, path: &[&str]) -> Option<Node<'a, 'input>> {
+    path.iter().try_fold(node, |node, name| {
+        node.children().find(|n| n.has_tag_name(*name))
+    })
+}
+
+fn text(node: Node, path: &[&str]) -> Option<String> {
+    find(node, path)
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_statement_with_batched_entry() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <Stmt>
+      <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id></Acct>
+      <Ntry>
+        <NtryRef>1</NtryRef>
+        <Amt Ccy="EUR">42.10</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <BookgDt><Dt>2023-01-31</Dt></BookgDt>
+        <NtryDtls><TxDtls>
+          <RltdPties><Cdtr><Nm>Supermarket GmbH</Nm></Cdtr></RltdPties>
+          <RmtInf><Ustrd>Card payment</Ustrd><Ustrd>1234</Ustrd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <NtryRef>2</NtryRef>
+        <Amt Ccy="EUR">300.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <BookgDt><DtTm>2023-02-01T10:00:00</DtTm></BookgDt>
+        <NtryDtls>
+          <TxDtls>
+            <AmtDtls><TxAmt><Amt Ccy="EUR">100.00</Amt></TxAmt></AmtDtls>
+            <RltdPties><Dbtr><Nm>Alice</Nm></Dbtr></RltdPties>
+          </TxDtls>
+          <TxDtls>
+            <AmtDtls><TxAmt><Amt Ccy="EUR">200.00</Amt></TxAmt></AmtDtls>
+            <RltdPties><Dbtr><Nm>Bob</Nm></Dbtr></RltdPties>
+          </TxDtls>
+        </NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">2.50</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <BookgDt><Dt>2023-02-02</Dt></BookgDt>
+        <AddtlNtryInf>Account fee</AddtlNtryInf>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+        assert!(is_camt(content));
+        let records = read(content).unwrap();
+
+        assert_eq!(4, records.len());
+        assert_eq!(
+            "Supermarket GmbH - Card payment 1234",
+            records[0].description()
+        );
+        assert_eq!(Decimal::new(-4210, 2), records[0].get_amount());
+        assert_eq!(&Some("EUR".to_string()), records[0].currency());
+        assert_eq!(
+            Some("DE89370400440532013000".to_string()),
+            records[0].account()
+        );
+        assert_eq!("Alice", records[1].description());
+        assert_eq!(Decimal::new(100, 0), records[1].get_amount());
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2023, 2, 1).unwrap(),
+            *records[2].date()
+        );
+        assert_eq!("Account fee", records[3].description());
+        assert_eq!(Decimal::new(-250, 2), records[3].get_amount());
+    }
+}
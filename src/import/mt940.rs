@@ -0,0 +1,214 @@
+use std::{error::Error, str::FromStr};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::Record;
+
+/// Check whether the content looks like a SWIFT MT940 statement.
+pub fn is_mt940(content: &str) -> bool {
+    content.contains(":61:") && (content.contains(":60F:") || content.contains(":60M:"))
+}
+
+/// Read the transactions in a MT940 statement.
+///
+/// Each `:61:` statement line becomes a record, with the description taken from
+/// the `:86:` information field that follows it, which can span multiple lines.
+pub fn read(content: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+    let mut records = Vec::new();
+    let mut pending: Option<(NaiveDate, Decimal)> = None;
+    let mut currency = None;
+    let mut account = None;
+
+    for (tag, value) in fields(content) {
+        match tag {
+            "25" => account = Some(value.trim().to_string()),
+            // Opening balance, e.g. `C230130EUR1000,00`
+            "60F" | "60M" => currency = value.get(7..10).map(|c| c.to_string()),
+            "61" => {
+                if let Some((date, amount)) = pending.take() {
+                    records.push(to_record(date, amount, String::new(), &currency, &account));
+                }
+                pending = Some(parse_statement_line(&value)?);
+            }
+            "86" => {
+                if let Some((date, amount)) = pending.take() {
+                    records.push(to_record(
+                        date,
+                        amount,
+                        parse_narrative(&value),
+                        &currency,
+                        &account,
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some((date, amount)) = pending.take() {
+        records.push(to_record(date, amount, String::new(), &currency, &account));
+    }
+
+    Ok(records)
+}
+
+fn to_record(
+    date: NaiveDate,
+    amount: Decimal,
+    description: String,
+    currency: &Option<String>,
+    account: &Option<String>,
+) -> Record {
+    let mut record = if amount.is_sign_negative() {
+        Record::new(date, description, Some(-amount), None, None)
+    } else {
+        Record::new(date, description, None, Some(amount), None)
+    };
+    record.set_currency(currency.clone());
+    record.set_account_number(account.clone());
+    record
+}
+
+/// Split the content into the tagged fields, joining continuation lines with a newline.
+fn fields(content: &str) -> Vec<(&str, String)> {
+    let mut fields: Vec<(&str, String)> = Vec::new();
+    for line in content.lines() {
+        let line = line.trim_end();
+        match parse_tag(line) {
+            Some((tag, value)) => fields.push((tag, value.to_string())),
+            None if line == "-" || line.starts_with('{') || line.starts_with('}') => {}
+            None => {
+                if let Some((_, value)) = fields.last_mut() {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+            }
+        }
+    }
+    fields
+}
+
+/// Parse a line starting with a tag such as `:61:` or `:60F:`.
+fn parse_tag(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix(':')?;
+    let (tag, value) = rest.split_once(':')?;
+    let valid = tag.is_ascii()
+        && (2..=3).contains(&tag.len())
+        && tag[..2].chars().all(|c| c.is_ascii_digit())
+        && tag[2..].chars().all(|c| c.is_ascii_uppercase());
+    valid.then_some((tag, value))
+}
+
+/// Parse the date and signed amount from a `:61:` statement line, e.g.
+/// `2301310131D12,50NMSCNONREF`.
+fn parse_statement_line(value: &str) -> Result<(NaiveDate, Decimal), Box<dyn Error>> {
+    let invalid = || format!("Invalid MT940 statement line ':61:{value}'");
+
+    let date = value
+        .get(..6)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%y%m%d").ok())
+        .ok_or_else(invalid)?;
+
+    let mut rest = &value[6..];
+    // Optional entry date
+    if rest.len() >= 4 && rest[..4].chars().all(|c| c.is_ascii_digit()) {
+        rest = &rest[4..];
+    }
+
+    let (is_debit, rest) = if let Some(rest) = rest.strip_prefix("RC") {
+        (true, rest)
+    } else if let Some(rest) = rest.strip_prefix("RD") {
+        (false, rest)
+    } else if let Some(rest) = rest.strip_prefix('C') {
+        (false, rest)
+    } else if let Some(rest) = rest.strip_prefix('D') {
+        (true, rest)
+    } else {
+        return Err(invalid().into());
+    };
+
+    // Optional funds code, being the last letter of the currency code
+    let rest = rest
+        .strip_prefix(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(rest);
+
+    let amount: String = rest
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == ',')
+        .collect();
+    let amount = Decimal::from_str(&amount.replace(',', ".")).map_err(|_| invalid())?;
+
+    Ok((date, if is_debit { -amount } else { amount }))
+}
+
+/// Turn the `:86:` information into a description.
+///
+/// Structured narratives (e.g. `005?00Card payment?20Reference?32Name`) are
+/// reduced to the counterparty name and the purpose, while free text lines are
+/// joined with spaces.
+fn parse_narrative(value: &str) -> String {
+    if !value.contains('?') {
+        return value.split_whitespace().collect::<Vec<&str>>().join(" ");
+    }
+
+    let joined = value.replace('\n', "");
+    let mut name = String::new();
+    let mut purpose = String::new();
+    for subfield in joined.split('?').skip(1) {
+        let code = subfield.get(..2).unwrap_or_default();
+        let text = subfield.get(2..).unwrap_or_default();
+        match code {
+            "20" | "21" | "22" | "23" | "24" | "25" | "26" | "27" | "28" | "29" => {
+                purpose.push_str(text)
+            }
+            "32" | "33" => name.push_str(text),
+            _ => {}
+        }
+    }
+
+    [name.trim(), purpose.trim()]
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" - ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_statement_with_multi_line_narrative() {
+        let content = ":20:STARTUMS
+:25:12345678/0001234567
+:28C:00001/001
+:60F:C230130EUR1000,00
+:61:2301310131D12,50NMSCNONREF//1234
+:86:TESCO STORES
+ CARD 1234
+:61:230201C2500,NTRFNONREF
+:86:005?00GUTSCHRIFT?20SALARY JANU?21ARY?32ACME
+ LTD
+:62F:C230201EUR3487,50
+-
+";
+
+        assert!(is_mt940(content));
+        let records = read(content).unwrap();
+
+        assert_eq!(2, records.len());
+        assert_eq!("TESCO STORES CARD 1234", records[0].description());
+        assert_eq!(Decimal::new(-1250, 2), records[0].get_amount());
+        assert_eq!(&Some("EUR".to_string()), records[0].currency());
+        assert_eq!(
+            Some("12345678/0001234567".to_string()),
+            records[0].account()
+        );
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2023, 1, 31).unwrap(),
+            *records[0].date()
+        );
+        assert_eq!("ACME LTD - SALARY JANUARY", records[1].description());
+        assert_eq!(Decimal::new(2500, 0), records[1].get_amount());
+    }
+}
@@ -0,0 +1,228 @@
+use std::{error::Error, str::FromStr};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::Record;
+
+const TRANSACTION_START: &str = "<STMTTRN>";
+const TRANSACTION_END: &str = "</STMTTRN>";
+/// Start of a bank statement and of a credit card statement.
+const STATEMENT_STARTS: [&str; 2] = ["<STMTRS>", "<CCSTMTRS>"];
+
+/// Check whether the content looks like an OFX or QFX statement.
+pub fn is_ofx(content: &str) -> bool {
+    let start = content.trim_start();
+    start.starts_with("OFXHEADER") || content.contains("<OFX>")
+}
+
+/// Read the transactions in an OFX/QFX statement.
+///
+/// Both the SGML based 1.x format, where elements are not closed, and the XML
+/// based 2.x format are supported, as each field is read up until the next tag.
+pub fn read(content: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+    let mut records = Vec::new();
+    for statement in statements(content) {
+        read_statement(statement, &mut records)?;
+    }
+    Ok(records)
+}
+
+/// Split the content into its bank and credit card statements, which each have
+/// their own account and currency.
+fn statements(content: &str) -> Vec<&str> {
+    let mut starts: Vec<usize> = STATEMENT_STARTS
+        .iter()
+        .flat_map(|tag| content.match_indices(tag).map(|(i, _)| i))
+        .collect();
+    starts.sort();
+    if starts.is_empty() {
+        return vec![content];
+    }
+    starts
+        .iter()
+        .zip(starts.iter().skip(1).chain([&content.len()]))
+        .map(|(start, end)| &content[*start..*end])
+        .collect()
+}
+
+/// Read the transactions of a single statement, using the account and currency
+/// in its header.
+fn read_statement(content: &str, records: &mut Vec<Record>) -> Result<(), Box<dyn Error>> {
+    let mut rest = content;
+    let header = Fields::parse(content.split(TRANSACTION_START).next().unwrap_or_default());
+    let currency = header.get("CURDEF").map(|c| c.to_string());
+    let sort_code = header.get("BANKID").map(|c| c.to_string());
+    let account_number = header.get("ACCTID").map(|c| c.to_string());
+
+    while let Some(start) = rest.find(TRANSACTION_START) {
+        let block = &rest[start + TRANSACTION_START.len()..];
+        let end = block.find(TRANSACTION_END).unwrap_or(block.len());
+        let fields = Fields::parse(&block[..end]);
+        rest = &block[end..];
+
+        let id = fields.get("FITID").unwrap_or_default();
+        let date = fields
+            .get("DTPOSTED")
+            .ok_or_else(|| format!("Transaction {id} is missing DTPOSTED"))
+            .and_then(|d| parse_date(d).map_err(|e| format!("Transaction {id}: {e}")))?;
+        let amount = fields
+            .get("TRNAMT")
+            .ok_or_else(|| format!("Transaction {id} is missing TRNAMT"))
+            .and_then(|a| {
+                Decimal::from_str(&a.replace(',', "."))
+                    .map_err(|e| format!("Transaction {id} has invalid TRNAMT '{a}': {e}"))
+            })?;
+
+        let name = fields.get("NAME").unwrap_or_default();
+        let memo = fields.get("MEMO").unwrap_or_default();
+        let description = match (name.is_empty(), memo.is_empty()) {
+            (false, false) if name != memo => format!("{name} - {memo}"),
+            (true, _) => memo.to_string(),
+            _ => name.to_string(),
+        };
+
+        let mut record = if amount.is_sign_negative() {
+            Record::new(date, description, Some(-amount), None, None)
+        } else {
+            Record::new(date, description, None, Some(amount), None)
+        };
+        record.set_currency(currency.clone());
+        record.set_sort_code(sort_code.clone());
+        record.set_account_number(account_number.clone());
+        record.set_bank_id(Some(id.to_string()).filter(|id| !id.is_empty()));
+        records.push(record);
+    }
+
+    Ok(())
+}
+
+/// OFX dates are `YYYYMMDD` optionally followed by a time and a timezone.
+fn parse_date(value: &str) -> Result<NaiveDate, Box<dyn Error>> {
+    value
+        .get(..8)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok())
+        .ok_or_else(|| format!("Invalid OFX date '{value}'").into())
+}
+
+/// The leaf elements of a single transaction.
+struct Fields<'a>(Vec<(&'a str, String)>);
+
+impl<'a> Fields<'a> {
+    fn parse(block: &'a str) -> Self {
+        Self(
+            block
+                .split('<')
+                .filter(|part| !part.starts_with('/'))
+                .filter_map(|part| part.split_once('>'))
+                .map(|(tag, value)| (tag.trim(), unescape(value.trim())))
+                .filter(|(_, value)| !value.is_empty())
+                .collect(),
+        )
+    }
+
+    fn get(&self, tag: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_sgml_statement() {
+        let content = r#"OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS><CURDEF>GBP<BANKACCTFROM><BANKID>112233<ACCTID>12345678<ACCTTYPE>CHECKING</BANKACCTFROM><BANKTRANLIST>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20230131120000.000[-5:EST]
+<TRNAMT>-12.50
+<FITID>1001
+<NAME>TESCO STORES
+<MEMO>CD 1234
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20230201
+<TRNAMT>2500.00
+<FITID>1002
+<NAME>ACME &amp; CO
+</STMTTRN>
+</BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+"#;
+
+        assert!(is_ofx(content));
+        let records = read(content).unwrap();
+
+        assert_eq!(2, records.len());
+        assert_eq!("TESCO STORES - CD 1234", records[0].description());
+        assert_eq!(&Some("GBP".to_string()), records[0].currency());
+        assert_eq!(Some("112233 12345678".to_string()), records[0].account());
+        assert!(records[0].is_in_account("12345678"));
+        assert_eq!(Decimal::new(-1250, 2), records[0].get_amount());
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2023, 1, 31).unwrap(),
+            *records[0].date()
+        );
+        assert_eq!("ACME & CO", records[1].description());
+        assert_eq!(Decimal::new(2500, 0), records[1].get_amount());
+    }
+
+    #[test]
+    fn read_xml_statement() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<?OFX OFXHEADER="200" VERSION="220"?>
+<OFX><CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS><BANKTRANLIST>
+<STMTTRN><TRNTYPE>DEBIT</TRNTYPE><DTPOSTED>20230115</DTPOSTED><TRNAMT>-20.00</TRNAMT><FITID>A1</FITID><MEMO>AMAZON</MEMO></STMTTRN>
+</BANKTRANLIST></CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1></OFX>"#;
+
+        assert!(is_ofx(content));
+        let records = read(content).unwrap();
+
+        assert_eq!(1, records.len());
+        assert_eq!("AMAZON", records[0].description());
+        assert_eq!(Decimal::new(-20, 0), records[0].get_amount());
+    }
+
+    #[test]
+    fn read_statements_of_several_accounts() {
+        let content = r#"<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS><CURDEF>GBP</CURDEF><BANKACCTFROM><BANKID>112233</BANKID><ACCTID>111</ACCTID></BANKACCTFROM><BANKTRANLIST>
+<STMTTRN><DTPOSTED>20230115</DTPOSTED><TRNAMT>-20.00</TRNAMT><FITID>A1</FITID><NAME>TESCO</NAME></STMTTRN>
+</BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1>
+<CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS><CURDEF>EUR</CURDEF><CCACCTFROM><ACCTID>222</ACCTID></CCACCTFROM><BANKTRANLIST>
+<STMTTRN><DTPOSTED>20230116</DTPOSTED><TRNAMT>-5.00</TRNAMT><FITID>B1</FITID><NAME>CAFE</NAME></STMTTRN>
+</BANKTRANLIST></CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1></OFX>"#;
+
+        let records = read(content).unwrap();
+
+        assert_eq!(
+            vec![
+                (Some("112233 111".to_string()), Some("GBP".to_string())),
+                (Some("222".to_string()), Some("EUR".to_string()))
+            ],
+            records
+                .iter()
+                .map(|r| (r.account(), r.currency().clone()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
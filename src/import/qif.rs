@@ -0,0 +1,245 @@
+use std::{error::Error, str::FromStr};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::{date::DateFormats, Record};
+
+/// Account types containing bank transactions. Other sections, such as
+/// category lists and investment accounts, are skipped.
+const TRANSACTION_TYPES: [&str; 5] = ["Bank", "Cash", "CCard", "Oth A", "Oth L"];
+
+/// Check whether the content looks like a Quicken QIF file.
+pub fn is_qif(content: &str) -> bool {
+    let start = content.trim_start_matches('\u{feff}').trim_start();
+    start.starts_with("!Type:") || start.starts_with("!Account") || start.starts_with("!Option")
+}
+
+/// Read the transactions in a QIF file.
+///
+/// Categories in the file are kept, with `:` replaced by `/` to match the
+/// nested categories used elsewhere. Split transactions become one record per
+/// split line, each with the amount and category of the split.
+pub fn read(content: &str, date_formats: &DateFormats) -> Result<Vec<Record>, Box<dyn Error>> {
+    let mut records = Vec::new();
+    let mut in_transactions = false;
+    let mut transaction = Transaction::default();
+
+    for (line_number, line) in content.lines().enumerate() {
+        let line = line.trim_matches('\u{feff}').trim_end();
+        let Some(code) = line.chars().next() else {
+            continue;
+        };
+        let value = line[code.len_utf8()..].trim();
+
+        if code == '!' {
+            if let Some(account_type) = value.strip_prefix("Type:") {
+                in_transactions = TRANSACTION_TYPES.contains(&account_type.trim());
+            }
+            continue;
+        }
+        if !in_transactions {
+            continue;
+        }
+
+        let error = |e: Box<dyn Error>| format!("{e} on line {}", line_number + 1);
+        match code {
+            'D' => transaction.date = Some(parse_date(value, date_formats).map_err(error)?),
+            'T' | 'U' => transaction.amount = Some(parse_amount(value).map_err(error)?),
+            'P' => transaction.payee = value.to_string(),
+            'M' => transaction.memo = value.to_string(),
+            'L' => transaction.category = parse_category(value),
+            'S' => transaction.splits.push(Split {
+                category: parse_category(value),
+                ..Default::default()
+            }),
+            'E' => {
+                if let Some(split) = transaction.splits.last_mut() {
+                    split.memo = value.to_string();
+                }
+            }
+            '$' => {
+                if let Some(split) = transaction.splits.last_mut() {
+                    split.amount = Some(parse_amount(value).map_err(error)?);
+                }
+            }
+            '^' => {
+                records.extend(
+                    std::mem::take(&mut transaction)
+                        .into_records()
+                        .map_err(|e| format!("{e} ending on line {}", line_number + 1))?,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    // The last transaction does not always end with a `^`
+    if !transaction.is_empty() {
+        records.extend(
+            transaction
+                .into_records()
+                .map_err(|e| format!("{e} ending at the end of the file"))?,
+        );
+    }
+
+    Ok(records)
+}
+
+#[derive(Debug, Default)]
+struct Transaction {
+    date: Option<NaiveDate>,
+    amount: Option<Decimal>,
+    payee: String,
+    memo: String,
+    category: Option<String>,
+    splits: Vec<Split>,
+}
+
+#[derive(Debug, Default)]
+struct Split {
+    amount: Option<Decimal>,
+    memo: String,
+    category: Option<String>,
+}
+
+impl Transaction {
+    fn is_empty(&self) -> bool {
+        self.date.is_none()
+            && self.amount.is_none()
+            && self.payee.is_empty()
+            && self.memo.is_empty()
+            && self.category.is_none()
+            && self.splits.is_empty()
+    }
+
+    fn into_records(self) -> Result<Vec<Record>, Box<dyn Error>> {
+        let date = self.date.ok_or("Transaction is missing a date")?;
+
+        if self.splits.is_empty() {
+            let amount = self.amount.ok_or("Transaction is missing an amount")?;
+            let description = describe(&self.payee, &self.memo);
+            return Ok(vec![to_record(date, description, amount, self.category)]);
+        }
+
+        if let Some(amount) = self.amount {
+            let total: Decimal = self.splits.iter().filter_map(|s| s.amount).sum();
+            if total != amount {
+                return Err(format!(
+                    "The splits of the transaction add up to {total}, instead of {amount}"
+                )
+                .into());
+            }
+        }
+
+        self.splits
+            .into_iter()
+            .map(|split| {
+                let amount = split.amount.ok_or("Split is missing an amount")?;
+                let memo = if split.memo.is_empty() {
+                    &self.memo
+                } else {
+                    &split.memo
+                };
+                let description = describe(&self.payee, memo);
+                Ok(to_record(date, description, amount, split.category))
+            })
+            .collect()
+    }
+}
+
+fn to_record(
+    date: NaiveDate,
+    description: String,
+    amount: Decimal,
+    category: Option<String>,
+) -> Record {
+    if amount.is_sign_negative() {
+        Record::new(date, description, Some(-amount), None, category)
+    } else {
+        Record::new(date, description, None, Some(amount), category)
+    }
+}
+
+fn describe(payee: &str, memo: &str) -> String {
+    match (payee.is_empty(), memo.is_empty()) {
+        (false, false) if payee != memo => format!("{payee} - {memo}"),
+        (true, _) => memo.to_string(),
+        _ => payee.to_string(),
+    }
+}
+
+/// QIF files often use an apostrophe for years after 2000, e.g. `1/31'23`.
+fn parse_date(value: &str, date_formats: &DateFormats) -> Result<NaiveDate, Box<dyn Error>> {
+    let value = match value.split_once('\'') {
+        Some((day_month, year)) if year.trim().len() == 2 => {
+            format!("{day_month}/20{}", year.trim())
+        }
+        Some((day_month, year)) => format!("{day_month}/{}", year.trim()),
+        None => value.to_string(),
+    };
+    date_formats.parse(&value.replace(' ', ""))
+}
+
+fn parse_amount(value: &str) -> Result<Decimal, Box<dyn Error>> {
+    Decimal::from_str(&value.replace(',', ""))
+        .map_err(|e| format!("Invalid amount '{value}': {e}").into())
+}
+
+/// Transfers to other accounts are written as `[Account]` and are not a category.
+fn parse_category(value: &str) -> Option<String> {
+    let category = value.split('/').next().unwrap_or_default().trim();
+    if category.is_empty() || category.starts_with('[') {
+        None
+    } else {
+        Some(category.replace(':', "/"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_transactions_with_splits() {
+        let content = "!Type:Cat
+NFood
+^
+!Type:Bank
+D31/01/2023
+T-42.50
+PSUPERMARKET
+LFood:Groceries
+SFood:Groceries
+$-30.00
+SHome:Household
+EBin bags
+$-12.50
+^
+D1/2'23
+T2,500.00
+PACME SALARY
+LIncome
+";
+
+        assert!(is_qif(content));
+        let records = read(content, &DateFormats::default()).unwrap();
+
+        assert_eq!(3, records.len());
+        assert_eq!(Decimal::new(-30, 0), records[0].get_amount());
+        assert_eq!(&Some("Food/Groceries".to_string()), records[0].category());
+        assert_eq!("SUPERMARKET - Bin bags", records[1].description());
+        assert_eq!(&Some("Home/Household".to_string()), records[1].category());
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2023, 2, 1).unwrap(),
+            *records[2].date()
+        );
+        assert_eq!(Decimal::new(2500, 0), records[2].get_amount());
+
+        let content = content.replace("$-12.50", "$-10.00");
+        let error = read(&content, &DateFormats::default()).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("add up to -40.00, instead of -42.50"));
+    }
+}
@@ -0,0 +1,520 @@
+use std::{error::Error, fs, str::FromStr};
+
+use csv::{QuoteStyle, ReaderBuilder, StringRecord, Terminator, WriterBuilder};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{date::DateFormats, record::tags_format, Record};
+
+/// Describes the layout of a CSV statement file from a specific bank.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Schema {
+    name: String,
+    #[serde(default = "default_delimiter")]
+    delimiter: char,
+    /// Separator between the whole and fractional part of amounts, either `.` or `,`.
+    /// The other one is taken to separate thousands.
+    #[serde(default = "default_decimal_separator")]
+    decimal_separator: char,
+    /// Number of lines before the header row.
+    #[serde(default)]
+    header_row: usize,
+    date: String,
+    /// Format of the dates, overriding the configured date formats.
+    #[serde(default)]
+    date_format: Option<String>,
+    description: String,
+    amount: Amount,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    currency: Option<String>,
+    #[serde(default)]
+    transaction_type: Option<String>,
+    #[serde(default)]
+    sort_code: Option<String>,
+    #[serde(default)]
+    account_number: Option<String>,
+    #[serde(default)]
+    balance: Option<String>,
+    #[serde(default)]
+    tags: Option<String>,
+}
+
+/// The column(s) holding the amount of a transaction.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Amount {
+    /// A single column with a signed amount.
+    Signed {
+        column: String,
+        #[serde(default)]
+        sign: SignConvention,
+    },
+    /// A pair of columns, where only one of them is filled for each transaction.
+    Split { debit: String, credit: String },
+}
+
+/// How to interpret the sign of a signed amount column.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignConvention {
+    /// Positive amounts are money coming into the account.
+    #[default]
+    CreditPositive,
+    /// Positive amounts are money leaving the account, as on most credit card statements.
+    DebitPositive,
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_decimal_separator() -> char {
+    '.'
+}
+
+impl Schema {
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// The columns that must be present for a file to match this schema.
+    fn required_columns(&self) -> Vec<&str> {
+        let mut columns = vec![self.date.as_str(), self.description.as_str()];
+        match &self.amount {
+            Amount::Signed { column, .. } => columns.push(column),
+            Amount::Split { debit, credit } => {
+                columns.push(debit);
+                columns.push(credit);
+            }
+        }
+        columns
+    }
+
+    fn reader<'a>(&self, content: &'a str) -> csv::Reader<&'a [u8]> {
+        ReaderBuilder::new()
+            .delimiter(self.delimiter as u8)
+            .flexible(true)
+            .from_reader(skip_lines(content, self.header_row).as_bytes())
+    }
+
+    /// Check whether the headers of the content matches this schema.
+    pub fn matches(&self, content: &str) -> bool {
+        self.reader(content)
+            .headers()
+            .map(|headers| {
+                self.required_columns()
+                    .iter()
+                    .all(|c| headers.iter().any(|h| h.trim() == *c))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Read all the records from the content using this schema.
+    pub fn read(
+        &self,
+        content: &str,
+        date_formats: &DateFormats,
+    ) -> Result<Vec<Record>, Box<dyn Error>> {
+        let date_formats = self
+            .date_format
+            .as_ref()
+            .map(|f| DateFormats::new(vec![f.clone()]))
+            .unwrap_or_else(|| date_formats.clone());
+        let mut reader = self.reader(content);
+        let headers = reader.headers()?.clone();
+        let index_of = |column: &str| -> Result<usize, Box<dyn Error>> {
+            headers
+                .iter()
+                .position(|h| h.trim() == column)
+                .ok_or_else(|| {
+                    format!("Missing column '{column}' for schema '{}'", self.name).into()
+                })
+        };
+
+        let date = index_of(&self.date)?;
+        let description = index_of(&self.description)?;
+        // Optional columns are ignored if they are not present in the file
+        let optional_index_of = |column: &Option<String>| {
+            column
+                .as_deref()
+                .and_then(|c| headers.iter().position(|h| h.trim() == c))
+        };
+        let category = optional_index_of(&self.category);
+        let currency = optional_index_of(&self.currency);
+        let transaction_type = optional_index_of(&self.transaction_type);
+        let sort_code = optional_index_of(&self.sort_code);
+        let account_number = optional_index_of(&self.account_number);
+        let balance = optional_index_of(&self.balance);
+        let tags = optional_index_of(&self.tags);
+        let amount = match &self.amount {
+            Amount::Signed { column, sign } => AmountIndex::Signed(index_of(column)?, *sign),
+            Amount::Split { debit, credit } => {
+                AmountIndex::Split(index_of(debit)?, index_of(credit)?)
+            }
+        };
+
+        let mut records = Vec::new();
+        for (row, result) in reader.records().enumerate() {
+            let line = result?;
+            let field = |i: usize| line.get(i).unwrap_or_default().trim();
+            let parse = |i: usize| {
+                parse_amount(field(i), self.decimal_separator)
+                    .map_err(|e| format!("Invalid amount on row {}: {e}", row + 1))
+            };
+
+            let (debit_amount, credit_amount) = match amount {
+                AmountIndex::Signed(i, sign) => match parse(i)? {
+                    Some(value) => {
+                        let value = if sign == SignConvention::DebitPositive {
+                            -value
+                        } else {
+                            value
+                        };
+                        if value.is_sign_negative() {
+                            (Some(-value), None)
+                        } else {
+                            (None, Some(value))
+                        }
+                    }
+                    None => (None, None),
+                },
+                AmountIndex::Split(debit, credit) => (
+                    parse(debit)?.map(|x| x.abs()),
+                    parse(credit)?.map(|x| x.abs()),
+                ),
+            };
+
+            let optional_field = |i: Option<usize>| {
+                i.map(field)
+                    .filter(|c| !c.is_empty())
+                    .map(|c| c.to_string())
+            };
+
+            let mut record = Record::new(
+                date_formats
+                    .parse(field(date))
+                    .map_err(|e| format!("{e} on row {}", row + 1))?,
+                field(description).to_string(),
+                debit_amount,
+                credit_amount,
+                optional_field(category),
+            );
+            record.set_currency(optional_field(currency));
+            record.set_transaction_type(optional_field(transaction_type));
+            record.set_sort_code(optional_field(sort_code));
+            record.set_account_number(optional_field(account_number));
+            record.set_balance(balance.map(parse).transpose()?.flatten());
+            record.set_tags(
+                tags.map(|i| tags_format::parse(field(i)))
+                    .unwrap_or_default(),
+            );
+            records.push(record);
+        }
+
+        Ok(records)
+    }
+}
+
+impl Schema {
+    /// Update the category and tags columns of the content with those of the
+    /// records, which must have been read from it using this schema. All the
+    /// other columns, and the lines before the header row, are kept as they are.
+    /// The columns are added if they are missing and needed. Returns `None` if
+    /// nothing changed.
+    pub fn write(
+        &self,
+        content: &str,
+        records: &[Record],
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        let mut reader = self.reader(content);
+        let mut headers = reader.headers()?.clone();
+        let rows: Vec<StringRecord> = reader.records().collect::<Result<_, _>>()?;
+        if rows.len() != records.len() {
+            return Err(format!(
+                "Expected {} rows to write for schema '{}', found {}",
+                records.len(),
+                self.name,
+                rows.len()
+            )
+            .into());
+        }
+
+        let mut column = |name: &Option<String>, default: &str, needed: bool| {
+            let name = name.as_deref().unwrap_or(default);
+            match headers.iter().position(|h| h.trim() == name) {
+                Some(i) => Some(i),
+                None if needed => {
+                    headers.push_field(name);
+                    Some(headers.len() - 1)
+                }
+                None => None,
+            }
+        };
+        let category = column(&self.category, "Category", true);
+        let tags = column(
+            &self.tags,
+            "Tags",
+            records.iter().any(|r| !r.tags().is_empty()),
+        );
+
+        // Keep the line endings of the file, and only quote fields that need it
+        let crlf = content.contains("\r\n");
+        let mut changed = false;
+        let mut writer = WriterBuilder::new()
+            .delimiter(self.delimiter as u8)
+            .flexible(true)
+            .terminator(if crlf {
+                Terminator::CRLF
+            } else {
+                Terminator::Any(b'\n')
+            })
+            .quote_style(QuoteStyle::Necessary)
+            .from_writer(Vec::new());
+        writer.write_record(&headers)?;
+        for (row, record) in rows.iter().zip(records) {
+            let mut fields: Vec<String> = row.iter().map(str::to_string).collect();
+            let mut set = |i: Option<usize>, value: String| {
+                if let Some(i) = i {
+                    if fields.len() <= i {
+                        fields.resize(i + 1, String::new());
+                    }
+                    if fields[i].trim() != value {
+                        fields[i] = value;
+                        changed = true;
+                    }
+                }
+            };
+            set(category, record.category().clone().unwrap_or_default());
+            set(tags, record.tags().join(";"));
+            writer.write_record(&fields)?;
+        }
+
+        if !changed {
+            return Ok(None);
+        }
+        let preamble = &content[..content.len() - skip_lines(content, self.header_row).len()];
+        let mut written = String::from_utf8(writer.into_inner()?)?;
+        if !content.ends_with('\n') {
+            written.truncate(written.trim_end_matches(['\r', '\n']).len());
+        }
+        Ok(Some(preamble.to_owned() + &written))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum AmountIndex {
+    Signed(usize, SignConvention),
+    Split(usize, usize),
+}
+
+/// Parse an amount, ignoring the thousands separators and whitespace.
+fn parse_amount(
+    value: &str,
+    decimal_separator: char,
+) -> Result<Option<Decimal>, rust_decimal::Error> {
+    let thousands_separator = if decimal_separator == ',' { '.' } else { ',' };
+    let value: String = value
+        .chars()
+        .filter(|c| *c != thousands_separator && !c.is_whitespace())
+        .map(|c| if c == decimal_separator { '.' } else { c })
+        .collect();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    Decimal::from_str(&value).map(Some)
+}
+
+fn skip_lines(content: &str, lines: usize) -> &str {
+    let mut rest = content;
+    for _ in 0..lines {
+        rest = rest.split_once('\n').map(|(_, r)| r).unwrap_or_default();
+    }
+    rest
+}
+
+/// Collection of schema profiles, in the order they are tried when detecting
+/// the format of a file.
+#[derive(Debug, Clone)]
+pub struct Schemas(Vec<Schema>);
+
+impl Schemas {
+    /// Load the schema profiles from a JSON file. The built-in profiles for
+    /// `sample.csv` and the plain credit card export are always available,
+    /// but are tried after the profiles from the file. If the file does not
+    /// exist, only the built-in profiles are used.
+    pub fn new(filename: impl AsRef<str>) -> Result<Self, Box<dyn Error>> {
+        let mut schemas: Vec<Schema> = match fs::read_to_string(filename.as_ref()) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| format!("Invalid schemas in {}: {e}", filename.as_ref()))?,
+            Err(_) => Vec::new(),
+        };
+        for schema in schemas.iter() {
+            if !matches!(schema.decimal_separator, '.' | ',') {
+                return Err(format!(
+                    "Invalid decimal separator '{}' for schema '{}' in {}, expected '.' or ','",
+                    schema.decimal_separator,
+                    schema.name,
+                    filename.as_ref()
+                )
+                .into());
+            }
+        }
+        schemas.extend(Self::default().0);
+        Ok(Self(schemas))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Schema> {
+        self.0.iter().find(|s| s.name == name)
+    }
+
+    pub fn detect(&self, content: &str) -> Option<&Schema> {
+        self.0.iter().find(|s| s.matches(content))
+    }
+}
+
+impl Default for Schemas {
+    fn default() -> Self {
+        Self(vec![
+            Schema {
+                name: "default".to_string(),
+                delimiter: default_delimiter(),
+                decimal_separator: default_decimal_separator(),
+                header_row: 0,
+                date: "Transaction Date".to_string(),
+                date_format: None,
+                description: "Transaction Description".to_string(),
+                amount: Amount::Split {
+                    debit: "Debit Amount".to_string(),
+                    credit: "Credit Amount".to_string(),
+                },
+                category: Some("Category".to_string()),
+                currency: Some("Currency".to_string()),
+                transaction_type: Some("Transaction Type".to_string()),
+                sort_code: Some("Sort Code".to_string()),
+                account_number: Some("Account Number".to_string()),
+                balance: Some("Balance".to_string()),
+                tags: Some("Tags".to_string()),
+            },
+            Schema {
+                name: "credit".to_string(),
+                delimiter: default_delimiter(),
+                decimal_separator: default_decimal_separator(),
+                header_row: 0,
+                date: "Date".to_string(),
+                date_format: None,
+                description: "Description".to_string(),
+                amount: Amount::Signed {
+                    column: "Amount".to_string(),
+                    sign: SignConvention::DebitPositive,
+                },
+                category: None,
+                currency: None,
+                transaction_type: None,
+                sort_code: None,
+                account_number: None,
+                balance: None,
+                tags: None,
+            },
+        ])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detect_default_and_credit_schemas() {
+        let schemas = Schemas::default();
+        let default = "Transaction Date,Transaction Type,Sort Code,Account Number,Transaction Description,Debit Amount,Credit Amount,Balance,Category\n";
+        let credit = "Date,Description,Amount\n";
+
+        assert_eq!("default", schemas.detect(default).unwrap().name());
+        assert_eq!("credit", schemas.detect(credit).unwrap().name());
+        assert!(schemas.detect("Foo,Bar\n").is_none());
+    }
+
+    #[test]
+    fn read_signed_amount_with_delimiter_and_header_offset() {
+        let schema: Schema = serde_json::from_str(
+            r#"{
+                "name": "bank",
+                "delimiter": ";",
+                "header_row": 2,
+                "date": "Booked",
+                "description": "Text",
+                "amount": { "column": "Amount" }
+            }"#,
+        )
+        .unwrap();
+        let content = "Account statement\nGenerated today\nBooked;Text;Amount\n01/02/2023;Salary;1,000.50\n02/02/2023;Coffee;-3.2\n";
+
+        let records = schema.read(content, &DateFormats::default()).unwrap();
+
+        assert_eq!(2, records.len());
+        assert_eq!(Decimal::new(100050, 2), records[0].get_amount());
+        assert_eq!(Decimal::new(-32, 1), records[1].get_amount());
+        assert_eq!("Coffee", records[1].description());
+    }
+
+    #[test]
+    fn read_decimal_comma() {
+        let schema: Schema = serde_json::from_str(
+            r#"{
+                "name": "bank",
+                "delimiter": ";",
+                "decimal_separator": ",",
+                "date": "Booked",
+                "description": "Text",
+                "amount": { "column": "Amount" }
+            }"#,
+        )
+        .unwrap();
+        let content = "Booked;Text;Amount\n01/02/2023;Salary;1.000,50\n02/02/2023;Coffee;-12,5\n";
+
+        let records = schema.read(content, &DateFormats::default()).unwrap();
+
+        assert_eq!(Decimal::new(100050, 2), records[0].get_amount());
+        assert_eq!(Decimal::new(-125, 1), records[1].get_amount());
+    }
+
+    #[test]
+    fn write_keeps_other_columns() {
+        let schema: Schema = serde_json::from_str(
+            r#"{
+                "name": "bank",
+                "delimiter": ";",
+                "header_row": 1,
+                "date": "Booked",
+                "description": "Text",
+                "amount": { "column": "Amount" }
+            }"#,
+        )
+        .unwrap();
+        let content = "Account statement\nBooked;Text;Amount;Reference\n01/02/2023;Salary;1,000.50;A1\n02/02/2023;Coffee;-3.2;B2\n";
+        let mut records = schema.read(content, &DateFormats::default()).unwrap();
+
+        assert_eq!(None, schema.write(content, &records).unwrap());
+
+        records[1].set_category("Food/Coffee".to_string());
+        assert_eq!(
+            Some("Account statement\nBooked;Text;Amount;Reference;Category\n01/02/2023;Salary;1,000.50;A1;\n02/02/2023;Coffee;-3.2;B2;Food/Coffee\n".to_string()),
+            schema.write(content, &records).unwrap()
+        );
+
+        let schema = Schema {
+            header_row: 0,
+            ..schema
+        };
+        let content =
+            "Booked;Text;Amount\r\n01/02/2023;\"Salary; May\";1,000.50\r\n02/02/2023;Coffee;-3.2";
+        let mut records = schema.read(content, &DateFormats::default()).unwrap();
+        records[1].set_category("Food".to_string());
+        assert_eq!(
+            Some("Booked;Text;Amount;Category\r\n01/02/2023;\"Salary; May\";1,000.50;\r\n02/02/2023;Coffee;-3.2;Food".to_string()),
+            schema.write(content, &records).unwrap()
+        );
+    }
+}
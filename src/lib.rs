@@ -1,8 +1,22 @@
 pub mod analyze;
 pub mod calc;
+pub mod categorize;
+pub mod classify;
 pub mod compare;
+pub mod date;
+pub mod exclusions;
+pub mod explain;
+pub mod fx;
+pub mod import;
+pub mod lookup;
 pub mod merge;
+pub mod normalize;
+pub mod period;
+pub mod recategorize;
 pub(crate) mod record;
+pub mod refunds;
+pub mod rules;
+pub mod splits;
 pub mod tree;
 pub mod utils;
 
@@ -0,0 +1,218 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    error::Error,
+};
+
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use regex::Regex;
+
+use crate::{
+    calc::Categorizer,
+    import::{self, ImportArgs, ImportOptions},
+    utils::Lookup,
+};
+
+/// Arguments for managing the lookup.
+#[derive(Debug, Args)]
+pub struct LookupArgs {
+    #[command(subcommand)]
+    command: LookupCommand,
+    /// Only show the changes, without saving them.
+    #[arg(long, global = true)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum LookupCommand {
+    /// List the categories with their number of entries.
+    List,
+    /// Rename a category, together with its subcategories.
+    Rename { from: String, to: String },
+    /// Move the entries of categories into another one.
+    Merge {
+        into: String,
+        #[arg(required = true)]
+        from: Vec<String>,
+    },
+    /// Delete the entries with a description matching the regular expression.
+    Delete { pattern: String },
+    /// List the entries not used by any record in the files, limited by the
+    /// account and date filters.
+    Unused {
+        #[arg(required = true)]
+        files: Vec<String>,
+        /// Delete the unused entries.
+        #[arg(long)]
+        delete: bool,
+        #[command(flatten)]
+        import: Box<ImportArgs>,
+    },
+}
+
+/// A change to a single entry of the lookup, where no new category means it is deleted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Change {
+    description: String,
+    old: String,
+    new: Option<String>,
+}
+
+pub fn run(args: &LookupArgs, categorizer: &mut Categorizer) -> Result<(), Box<dyn Error>> {
+    let changes = match &args.command {
+        LookupCommand::List => {
+            list(categorizer.lookup());
+            return Ok(());
+        }
+        LookupCommand::Rename { from, to } => rename(categorizer.lookup(), from, to),
+        LookupCommand::Merge { into, from } => merge(categorizer.lookup(), into, from),
+        LookupCommand::Delete { pattern } => {
+            let pattern = Regex::new(pattern)?;
+            changes(categorizer.lookup(), |description, _| {
+                pattern.is_match(description).then_some(None)
+            })
+        }
+        LookupCommand::Unused { files, import, .. } => {
+            let opts: ImportOptions = import.as_ref().try_into()?;
+            let mut used = HashSet::new();
+            for filename in files {
+                let records = import::read_records(filename, &opts)?;
+                for mut record in records.into_iter().filter(|r| opts.includes(r)) {
+                    categorizer.normalize(&mut record);
+                    used.insert(record.get_payee().to_owned());
+                    used.insert(record.description().to_owned());
+                }
+            }
+            changes(categorizer.lookup(), |description, _| {
+                (!used.contains(description)).then_some(None)
+            })
+        }
+    };
+
+    // Unused entries are only listed, unless asked to delete them
+    let list_only = matches!(args.command, LookupCommand::Unused { delete: false, .. });
+    for change in changes.iter() {
+        match &change.new {
+            _ if list_only => println!("{:<40}{}", change.description, change.old.cyan()),
+            Some(new) => println!(
+                "{:<40}{} -> {}",
+                change.description,
+                change.old.red(),
+                new.green()
+            ),
+            None => println!(
+                "{:<40}{} -> {}",
+                change.description,
+                change.old.red(),
+                "deleted".yellow()
+            ),
+        }
+    }
+    match (list_only, args.dry_run) {
+        (true, _) => println!(
+            "{} entries unused, use --delete to delete them",
+            changes.len()
+        ),
+        (false, true) => println!("{} entries would be changed", changes.len()),
+        (false, false) => println!("{} entries changed", changes.len()),
+    }
+
+    if !list_only && !args.dry_run {
+        let lookup = categorizer.lookup_mut();
+        for change in changes {
+            match change.new {
+                Some(new) => lookup.insert(change.description, new),
+                None => lookup.remove(&change.description),
+            };
+        }
+    }
+
+    Ok(())
+}
+
+fn list(lookup: &Lookup) {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for category in lookup.values() {
+        *counts.entry(category).or_default() += 1;
+    }
+    for (category, count) in counts {
+        println!("{:<40}{count:>6}", category.cyan());
+    }
+}
+
+/// Get the changes to the entries, where `change` gives the new category of an
+/// entry or `None` if it is left as is.
+fn changes<F>(lookup: &Lookup, change: F) -> Vec<Change>
+where
+    F: Fn(&str, &str) -> Option<Option<String>>,
+{
+    let mut changes: Vec<Change> = lookup
+        .iter()
+        .filter_map(|(description, category)| {
+            change(description, category).map(|new| Change {
+                description: description.to_owned(),
+                old: category.to_owned(),
+                new,
+            })
+        })
+        .collect();
+    changes.sort_by(|a, b| a.description.cmp(&b.description));
+    changes
+}
+
+fn rename(lookup: &Lookup, from: &str, to: &str) -> Vec<Change> {
+    changes(lookup, |_, category| {
+        if category == from {
+            Some(Some(to.to_owned()))
+        } else {
+            category
+                .strip_prefix(from)
+                .filter(|rest| rest.starts_with('/'))
+                .map(|rest| Some(format!("{to}{rest}")))
+        }
+    })
+}
+
+fn merge(lookup: &Lookup, into: &str, from: &[String]) -> Vec<Change> {
+    changes(lookup, |_, category| {
+        from.iter()
+            .any(|f| f == category)
+            .then(|| Some(into.to_owned()))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rename_subtree() {
+        let lookup = Lookup::from(
+            [
+                ("pizza place", "Food/Eating out"),
+                ("burger bar", "Food/Eating out/Fast food"),
+                ("eating outdoors shop", "Food/Eating outdoors"),
+                ("tesco", "Food/Groceries"),
+            ]
+            .map(|(d, c)| (d.to_string(), c.to_string())),
+        );
+
+        let changes = rename(&lookup, "Food/Eating out", "Food/Restaurant");
+
+        assert_eq!(
+            vec![
+                Change {
+                    description: "burger bar".to_string(),
+                    old: "Food/Eating out/Fast food".to_string(),
+                    new: Some("Food/Restaurant/Fast food".to_string()),
+                },
+                Change {
+                    description: "pizza place".to_string(),
+                    old: "Food/Eating out".to_string(),
+                    new: Some("Food/Restaurant".to_string()),
+                },
+            ],
+            changes
+        );
+    }
+}
@@ -3,9 +3,16 @@ use std::{error::Error, fs};
 use clap::{Parser, Subcommand};
 use finance_analyzer::{
     analyze::{self, AnalyzeArgs},
+    calc::{Categorizer, NonInteractive},
+    categorize::{self, CategorizeArgs},
     compare::{self, CompareArgs},
+    explain::{self, ExplainArgs},
+    lookup::{self, LookupArgs},
     merge::{self, MergeArgs},
-    utils::{get_initial_lookup, Lookup},
+    normalize::Normalizer,
+    recategorize::{self, RecategorizeArgs},
+    rules::Rules,
+    utils::get_initial_lookup,
     PRECISION,
 };
 
@@ -15,6 +22,23 @@ struct Arguments {
     command: Commands,
     #[arg(short, long, default_value = "lookup.json")]
     lookup: String,
+    /// File with the ordered rules used to categorize records.
+    #[arg(short, long, default_value = "rules.json")]
+    rules: String,
+    /// File with the settings used to turn descriptions into canonical payees.
+    #[arg(short, long, default_value = "normalize.json")]
+    normalize: String,
+    /// Categorize records automatically when the best suggestion is at least
+    /// this confident (between 0 and 1), and tag them for review.
+    #[arg(long)]
+    auto_threshold: Option<f64>,
+    /// Never ask for categories, and handle the records without one with this policy.
+    /// Exits with an error if any record was left uncategorized.
+    #[arg(long, value_enum)]
+    non_interactive: Option<NonInteractive>,
+    /// Category used for the records without one with `--non-interactive uncategorized`.
+    #[arg(long, default_value = "Uncategorized")]
+    uncategorized_category: String,
     #[arg(short, long = "precision", default_value = "0")]
     precision: usize,
 }
@@ -25,24 +49,50 @@ enum Commands {
     Analyze(AnalyzeArgs),
     Compare(CompareArgs),
     Merge(MergeArgs),
+    /// Show how the records in a file are categorized.
+    Explain(ExplainArgs),
+    /// Categorize the records in the files, asking once per payee.
+    Categorize(CategorizeArgs),
+    /// Manage the categories in the lookup.
+    Lookup(LookupArgs),
+    /// Apply the current rules and lookup to files that are already categorized.
+    Recategorize(RecategorizeArgs),
 }
 
 /// Entrypoint
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Arguments::parse();
-    let mut lookup: Lookup = get_initial_lookup(&args.lookup);
+    let mut categorizer = Categorizer::new(
+        get_initial_lookup(&args.lookup),
+        Rules::new(&args.rules)?,
+        Normalizer::new(&args.normalize)?,
+    );
+    categorizer.set_threshold(args.auto_threshold);
+    categorizer.set_non_interactive(args.non_interactive, args.uncategorized_category.clone());
     // SAFETY: Done right at startup before anything else has happened,
     // so nothing can conflict with writing to this static variable.
     *PRECISION.write().unwrap() = args.precision;
 
-    match &args.command {
-        Commands::Analyze(args) => analyze::run(args, &mut lookup)?,
-        Commands::Compare(args) => compare::run(args, &mut lookup),
-        Commands::Merge(args) => merge::run(args)?,
+    let result = match &args.command {
+        Commands::Analyze(args) => analyze::run(args, &mut categorizer),
+        Commands::Compare(args) => compare::run(args, &mut categorizer),
+        Commands::Merge(args) => merge::run(args),
+        Commands::Explain(args) => explain::run(args, &categorizer),
+        Commands::Categorize(args) => categorize::run(args, &mut categorizer),
+        Commands::Lookup(args) => lookup::run(args, &mut categorizer),
+        Commands::Recategorize(args) => recategorize::run(args, &mut categorizer),
     };
 
-    // Save lookup dictionary
-    fs::write(&args.lookup, serde_json::to_string_pretty(&lookup)?)?;
+    // Save lookup dictionary, also when the command failed so answers are not lost
+    fs::write(
+        &args.lookup,
+        serde_json::to_string_pretty(categorizer.lookup())?,
+    )?;
+    result?;
+
+    if !categorizer.missing().is_empty() {
+        return Err(categorizer.missing_error());
+    }
 
     Ok(())
 }
@@ -1,9 +1,17 @@
-use std::{error::Error, fs::File};
+pub mod duplicates;
+pub mod transfers;
+
+use std::{error::Error, path::Path};
 
 use clap::{self, Args};
-use csv::{Reader, Writer};
+use colored::Colorize;
+use csv::Writer;
 
-use crate::{record::CreditRecord, Record};
+use self::duplicates::DuplicatePolicy;
+use crate::{
+    import::{self, ImportArgs, ImportOptions},
+    utils::format_with_color,
+};
 
 /// Merge arguments
 #[derive(Debug, Args)]
@@ -11,24 +19,75 @@ pub struct MergeArgs {
     files: Vec<String>,
     #[arg(short, long, help = "Output file to save result to")]
     output: String,
+    /// How to handle transactions found in more than one of the files.
+    #[arg(long, value_enum, default_value_t)]
+    duplicates: DuplicatePolicy,
+    /// Maximum number of days between the two sides of a transfer between accounts.
+    #[arg(long, default_value = "3")]
+    transfer_window: i64,
+    #[command(flatten)]
+    import: ImportArgs,
 }
 
 /// Merge the files.
 pub fn run(args: &MergeArgs) -> Result<(), Box<dyn Error>> {
-    let mut records = Vec::new();
+    let opts: ImportOptions = (&args.import).try_into()?;
+
+    let mut sources = Vec::new();
     for filename in args.files.iter() {
-        let mut reader = Reader::from_path(filename.as_str())?;
-        let is_full_file = reader.headers()?.iter().any(|x| x.trim() == "Sort Code");
-
-        records.extend(if is_full_file {
-            deserialize_normal(reader)?
-        } else {
-            deserialize_credit(reader)?
-        });
+        // Tag records without an account, e.g. from credit card statements,
+        // with the name of the file they came from.
+        let account = Path::new(filename)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string());
+        let mut records = Vec::new();
+        for mut record in import::read_records(filename, &opts)? {
+            if record.account().is_none() {
+                record.set_account_number(account.clone());
+            }
+            if opts.includes(&record) {
+                records.push(record);
+            }
+        }
+        sources.push(records);
+    }
+
+    if args.duplicates != DuplicatePolicy::Keep {
+        let duplicates = duplicates::find(&sources);
+        for duplicate in duplicates.iter() {
+            let record = &sources[*duplicate.source()][*duplicate.index()];
+            println!(
+                "Duplicate {} {} {} in {}, already in {}",
+                record.date().to_string().cyan(),
+                record.description().green(),
+                format_with_color(record.get_amount()),
+                args.files[*duplicate.source()],
+                args.files[*duplicate.original_source()],
+            );
+        }
+
+        if args.duplicates == DuplicatePolicy::Drop {
+            for duplicate in duplicates.iter().rev() {
+                sources[*duplicate.source()].remove(*duplicate.index());
+            }
+        }
     }
 
+    let mut records: Vec<_> = sources.into_iter().flatten().collect();
     records.sort_by(|a, z| a.date().cmp(z.date()));
 
+    for (debit, credit) in transfers::tag(&mut records, args.transfer_window) {
+        let (debit, credit) = (&records[debit], &records[credit]);
+        println!(
+            "Transfer {} {} from {} {} to {}",
+            format_with_color(debit.get_amount()),
+            debit.date().to_string().cyan(),
+            debit.account().unwrap_or_default(),
+            credit.date().to_string().cyan(),
+            credit.account().unwrap_or_default(),
+        );
+    }
+
     // Write records to output file
     let mut writer = Writer::from_path(args.output.as_str())?;
     for record in records {
@@ -38,22 +97,3 @@ pub fn run(args: &MergeArgs) -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
-
-fn deserialize_normal(mut reader: Reader<File>) -> Result<Vec<Record>, Box<dyn Error>> {
-    let mut values = Vec::new();
-    for record in reader.deserialize() {
-        values.push(record?);
-    }
-
-    Ok(values)
-}
-
-fn deserialize_credit(mut reader: Reader<File>) -> Result<Vec<Record>, Box<dyn Error>> {
-    let mut values = Vec::new();
-    for record in reader.deserialize() {
-        let credit_record: CreditRecord = record?;
-        values.push(Into::<Record>::into(credit_record));
-    }
-
-    Ok(values)
-}
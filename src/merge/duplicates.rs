@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use derive_getters::Getters;
+use rust_decimal::Decimal;
+
+use crate::Record;
+
+/// What to do with transactions that appear in more than one of the merged files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DuplicatePolicy {
+    /// Keep all the transactions.
+    Keep,
+    /// Keep all the transactions, but list the suspected duplicates.
+    #[default]
+    Report,
+    /// List the suspected duplicates and leave them out of the output.
+    Drop,
+}
+
+/// A record in one file, which is the same transaction as a record in an earlier file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Getters)]
+pub struct Duplicate {
+    source: usize,
+    index: usize,
+    original_source: usize,
+    original_index: usize,
+}
+
+/// Find the records that are the same transaction as a record in an earlier source.
+///
+/// Transactions match when they have the same date, amount and normalized
+/// description, and the same balance if both records have one. Records within
+/// the same source are never considered duplicates of each other, as the same
+/// purchase can happen twice on a day.
+pub fn find(sources: &[Vec<Record>]) -> Vec<Duplicate> {
+    let mut seen: HashMap<(NaiveDate, Decimal, String), Vec<(usize, usize)>> = HashMap::new();
+    let mut duplicates = Vec::new();
+
+    for (source, records) in sources.iter().enumerate() {
+        let mut unmatched = Vec::new();
+        for (index, record) in records.iter().enumerate() {
+            let key = key(record);
+            let candidates = seen.entry(key.clone()).or_default();
+            let matched = candidates.iter().position(|(s, i)| {
+                match (sources[*s][*i].balance(), record.balance()) {
+                    (Some(a), Some(b)) => a == b,
+                    _ => true,
+                }
+            });
+
+            match matched {
+                Some(position) => {
+                    // Each earlier record can only match a single duplicate
+                    let (original_source, original_index) = candidates.remove(position);
+                    duplicates.push(Duplicate {
+                        source,
+                        index,
+                        original_source,
+                        original_index,
+                    });
+                }
+                None => unmatched.push((key, (source, index))),
+            }
+        }
+
+        // Only make the records available for matching once the whole source has been processed.
+        for (key, position) in unmatched {
+            seen.entry(key).or_default().push(position);
+        }
+    }
+
+    duplicates
+}
+
+fn key(record: &Record) -> (NaiveDate, Decimal, String) {
+    (
+        *record.date(),
+        record.get_original_amount().normalize(),
+        normalize(record.description()),
+    )
+}
+
+/// Normalize a description so that differences in case, punctuation and
+/// whitespace between statement formats are ignored.
+fn normalize(description: &str) -> String {
+    description
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(day: u32, description: &str, amount: i64, balance: Option<i64>) -> Record {
+        let mut record = Record::new(
+            NaiveDate::from_ymd_opt(2023, 1, day).unwrap(),
+            description.to_string(),
+            Some(Decimal::new(amount, 2)),
+            None,
+            None,
+        );
+        record.set_balance(balance.map(|b| Decimal::new(b, 2)));
+        record
+    }
+
+    #[test]
+    fn find_duplicates_across_sources() {
+        let first = vec![
+            record(30, "COFFEE SHOP", 250, None),
+            record(31, "Tesco Stores", 1250, Some(10000)),
+            record(31, "Tesco Stores", 1250, Some(8750)),
+        ];
+        let second = vec![
+            record(31, "TESCO  STORES", 1250, Some(8750)),
+            record(31, "TESCO STORES", 1250, Some(5000)),
+            record(31, "COFFEE SHOP", 250, None),
+            record(30, "COFFEE SHOP", 250, None),
+            record(30, "COFFEE SHOP", 250, None),
+        ];
+
+        let duplicates = find(&[first, second]);
+
+        assert_eq!(
+            vec![
+                Duplicate {
+                    source: 1,
+                    index: 0,
+                    original_source: 0,
+                    original_index: 2
+                },
+                Duplicate {
+                    source: 1,
+                    index: 3,
+                    original_source: 0,
+                    original_index: 0
+                },
+            ],
+            duplicates
+        );
+    }
+}
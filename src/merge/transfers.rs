@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+
+use crate::Record;
+
+/// Tag set on both records of a matched transfer.
+pub const TRANSFER: &str = "transfer";
+
+/// Find and tag internal transfers between accounts, e.g. paying off a credit card
+/// from the current account.
+///
+/// A debit is paired with a credit of the same amount on a different account,
+/// dated at most `window` days apart. If there are several candidates the one
+/// closest in time is used. Returns the indices of the matched pairs, as
+/// (debit, credit).
+pub fn tag(records: &mut [Record], window: i64) -> Vec<(usize, usize)> {
+    let accounts: Vec<Option<String>> = records.iter().map(|r| r.account()).collect();
+    // Credits by amount, so each debit only looks at the credits it could match
+    let mut credits: HashMap<Decimal, Vec<usize>> = HashMap::new();
+    for (i, record) in records.iter().enumerate() {
+        let amount = record.get_amount();
+        if amount.is_sign_positive() && !amount.is_zero() {
+            credits.entry(amount).or_default().push(i);
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let mut matched = vec![false; records.len()];
+
+    for debit in 0..records.len() {
+        let amount = records[debit].get_amount();
+        if !amount.is_sign_negative() {
+            continue;
+        }
+
+        let credit = credits
+            .get(&-amount)
+            .into_iter()
+            .flatten()
+            .filter(|i| !matched[**i])
+            .filter(|i| accounts[**i] != accounts[debit])
+            .map(|i| {
+                let days = (*records[*i].date() - *records[debit].date())
+                    .num_days()
+                    .abs();
+                (*i, days)
+            })
+            .filter(|(_, days)| *days <= window)
+            .min_by_key(|(_, days)| *days)
+            .map(|(i, _)| i);
+
+        if let Some(credit) = credit {
+            matched[debit] = true;
+            matched[credit] = true;
+            pairs.push((debit, credit));
+        }
+    }
+
+    for (debit, credit) in pairs.iter() {
+        records[*debit].add_tag(TRANSFER);
+        records[*credit].add_tag(TRANSFER);
+    }
+
+    pairs
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn record(day: u32, account: &str, amount: i64) -> Record {
+        let amount = Decimal::new(amount, 0);
+        let mut record = Record::new(
+            NaiveDate::from_ymd_opt(2023, 1, day).unwrap(),
+            String::new(),
+            amount.is_sign_negative().then_some(-amount),
+            amount.is_sign_positive().then_some(amount),
+            None,
+        );
+        record.set_account_number(Some(account.to_string()));
+        record
+    }
+
+    #[test]
+    fn tag_opposite_amounts_across_accounts() {
+        let mut records = vec![
+            record(1, "current", -500),
+            record(2, "current", 500),
+            record(3, "card", 500),
+            record(20, "current", -100),
+            record(28, "card", 100),
+        ];
+
+        let pairs = tag(&mut records, 3);
+
+        assert_eq!(vec![(0, 2)], pairs);
+        assert!(records[0].has_tag(TRANSFER));
+        assert!(!records[1].has_tag(TRANSFER));
+        assert!(records[2].has_tag(TRANSFER));
+        assert!(!records[3].has_tag(TRANSFER));
+    }
+}
@@ -0,0 +1,98 @@
+use std::{error::Error, fs};
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+/// Turns bank descriptions into a canonical payee, so that variants with
+/// reference numbers, dates and card numbers end up with the same lookup key.
+///
+/// The strip patterns are removed first, then the text is optionally lower cased
+/// and stripped of digits, and finally runs of whitespace are collapsed.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Normalizer {
+    #[serde(default, deserialize_with = "deserialize_patterns")]
+    strip: Vec<Regex>,
+    #[serde(default = "enabled")]
+    fold_case: bool,
+    #[serde(default = "enabled")]
+    collapse_digits: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn deserialize_patterns<'de, D>(deserializer: D) -> Result<Vec<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let patterns: Vec<String> = Deserialize::deserialize(deserializer)?;
+    patterns
+        .iter()
+        .map(|p| Regex::new(p).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self {
+            strip: Vec::new(),
+            fold_case: true,
+            collapse_digits: true,
+        }
+    }
+}
+
+impl Normalizer {
+    /// Load the normalization settings from a JSON file. If the file does not
+    /// exist, the default settings are used.
+    pub fn new(filename: impl AsRef<str>) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(filename.as_ref()) {
+            Ok(content) => Ok(serde_json::from_str(&content)
+                .map_err(|e| format!("Invalid normalization in {}: {e}", filename.as_ref()))?),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Get the canonical payee for a description. If nothing is left after the
+    /// normalization, the trimmed description is used instead.
+    pub fn normalize(&self, description: &str) -> String {
+        let mut payee = description.to_string();
+        for pattern in self.strip.iter() {
+            payee = pattern.replace_all(&payee, " ").to_string();
+        }
+        if self.fold_case {
+            payee = payee.to_lowercase();
+        }
+        if self.collapse_digits {
+            payee.retain(|c| !c.is_ascii_digit());
+        }
+
+        let payee = payee.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if payee.is_empty() {
+            description.trim().to_string()
+        } else {
+            payee
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalize_variants_to_same_payee() {
+        let normalizer: Normalizer = serde_json::from_str(
+            r#"{ "strip": ["(?i)\\bCD \\d{4}\\b", "\\d{2}/\\d{2}/\\d{2,4}"] }"#,
+        )
+        .unwrap();
+
+        assert_eq!("tesco stores", normalizer.normalize("TESCO STORES 3345"));
+        assert_eq!(
+            "tesco stores",
+            normalizer.normalize("TESCO  STORES 1021 CD 1234 31/01/23")
+        );
+        assert_eq!("12345", normalizer.normalize(" 12345 "));
+    }
+}
@@ -0,0 +1,348 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use clap::{Args, ValueEnum};
+use derive_getters::Getters;
+
+/// Length of the periods that records are grouped into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Granularity {
+    /// ISO weeks, starting on Monday.
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Granularity {
+    /// Number of months in a period, or `None` for weeks.
+    fn months(&self) -> Option<i32> {
+        match self {
+            Self::Week => None,
+            Self::Month => Some(1),
+            Self::Quarter => Some(3),
+            Self::Year => Some(12),
+        }
+    }
+}
+
+/// The day that months start on, e.g. the day the salary is paid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonthStart {
+    /// A day of the month, or the last day of shorter months.
+    Day(u32),
+    /// The last day of the previous month that is not a weekend.
+    LastWorkingDay,
+}
+
+impl FromStr for MonthStart {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "last-working-day" => Ok(Self::LastWorkingDay),
+            day => match day.parse() {
+                Ok(day @ 1..=31) => Ok(Self::Day(day)),
+                _ => Err(format!(
+                    "Invalid month start '{s}', expected a day between 1 and 31 or last-working-day"
+                )),
+            },
+        }
+    }
+}
+
+/// Where months and years start. Months are named after the calendar month
+/// most of their days fall in, so with salary on the 25th of January, the
+/// month from then is February.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Calendar {
+    month_start: MonthStart,
+    /// Calendar month, from 1 to 12, that years and quarters start in.
+    year_start: u32,
+}
+
+impl Calendar {
+    pub fn new(month_start: MonthStart, year_start: u32) -> Self {
+        Self {
+            month_start,
+            year_start,
+        }
+    }
+
+    /// First day of the month with the given index, see `month_index`.
+    fn start_of(&self, month: i32) -> NaiveDate {
+        match self.month_start {
+            MonthStart::Day(day) if day <= 15 => clamped(month, day),
+            // Late starts belong to the next month, as most of their days are in it
+            MonthStart::Day(day) => clamped(month - 1, day),
+            MonthStart::LastWorkingDay => {
+                let mut date = first_day(month) - Days::new(1);
+                while matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+                    date = date - Days::new(1);
+                }
+                date
+            }
+        }
+    }
+
+    /// Index of the month the date is in, according to the month start.
+    fn month_of(&self, date: NaiveDate) -> i32 {
+        let month = month_index(date);
+        (month - 1..=month + 1)
+            .rev()
+            .find(|m| self.start_of(*m) <= date)
+            .expect("a month starts at most a month before the calendar month")
+    }
+}
+
+impl Default for Calendar {
+    fn default() -> Self {
+        Self::new(MonthStart::Day(1), 1)
+    }
+}
+
+/// Months counted from year 0, so that consecutive months differ by one.
+fn month_index(date: NaiveDate) -> i32 {
+    date.year() * 12 + date.month0() as i32
+}
+
+fn first_day(month: i32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(month.div_euclid(12), month.rem_euclid(12) as u32 + 1, 1)
+        .expect("the first day of a month is always valid")
+}
+
+/// The day of the month, or the last day if the month is shorter.
+fn clamped(month: i32, day: u32) -> NaiveDate {
+    let first = first_day(month);
+    let last = first_day(month + 1) - Days::new(1);
+    first.with_day(day).unwrap_or(last)
+}
+
+/// Arguments to group records into periods.
+#[derive(Debug, Args)]
+pub struct PeriodArgs {
+    /// Group the records by the period of their date. `compare` then compares the
+    /// periods of all the files instead of the files, and `analyze` shows the
+    /// details of each period instead of the whole file.
+    #[arg(long, value_enum)]
+    by: Option<Granularity>,
+    /// Day of the month that months start on, or `last-working-day`.
+    #[arg(long, default_value = "1", requires = "by")]
+    month_start: MonthStart,
+    /// Month, from 1 to 12, that years and quarters start in, e.g. 4 for April.
+    #[arg(
+        long,
+        default_value = "1",
+        requires = "by",
+        value_parser = clap::value_parser!(u32).range(1..=12)
+    )]
+    year_start: u32,
+}
+
+impl PeriodArgs {
+    pub fn granularity(&self) -> Option<Granularity> {
+        self.by
+    }
+}
+
+impl From<&PeriodArgs> for Calendar {
+    fn from(value: &PeriodArgs) -> Self {
+        Calendar::new(value.month_start, value.year_start)
+    }
+}
+
+/// A range of dates, from `start` to `end` inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Getters)]
+pub struct Period {
+    start: NaiveDate,
+    end: NaiveDate,
+    granularity: Granularity,
+    /// Index of the first month of the period, not used for weeks.
+    #[getter(skip)]
+    month: i32,
+    #[getter(skip)]
+    calendar: Calendar,
+}
+
+impl Period {
+    /// The period of the given granularity that the date falls in.
+    pub fn containing(date: NaiveDate, granularity: Granularity, calendar: Calendar) -> Self {
+        let Some(months) = granularity.months() else {
+            let start = date - Days::new(date.weekday().num_days_from_monday().into());
+            return Self {
+                start,
+                end: start + Days::new(6),
+                granularity,
+                month: month_index(start),
+                calendar,
+            };
+        };
+
+        let month = calendar.month_of(date);
+        let month = month - (month - (calendar.year_start as i32 - 1)).rem_euclid(months);
+        Self {
+            start: calendar.start_of(month),
+            end: calendar.start_of(month + months) - Days::new(1),
+            granularity,
+            month,
+            calendar,
+        }
+    }
+
+    /// The period directly after this one.
+    pub fn next(&self) -> Self {
+        Self::containing(self.end + Days::new(1), self.granularity, self.calendar)
+    }
+
+    pub fn contains(&self, date: &NaiveDate) -> bool {
+        self.start <= *date && *date <= self.end
+    }
+
+    /// Number of days in the period.
+    pub fn days(&self) -> i64 {
+        (self.end - self.start).num_days() + 1
+    }
+
+    /// All the periods from the one containing `from` up to the one containing `to`.
+    pub fn between(
+        from: NaiveDate,
+        to: NaiveDate,
+        granularity: Granularity,
+        calendar: Calendar,
+    ) -> Vec<Self> {
+        let mut periods = vec![Self::containing(from, granularity, calendar)];
+        while periods.last().map(|p| p.end < to).unwrap_or(false) {
+            periods.push(periods[periods.len() - 1].next());
+        }
+        periods
+    }
+
+    /// The period limited to the dates from `from` to `to`, e.g. for the first and
+    /// last periods of a statement, which usually only cover part of theirs.
+    pub fn trim(mut self, from: NaiveDate, to: NaiveDate) -> Self {
+        self.start = self.start.max(from);
+        self.end = self.end.min(to);
+        self
+    }
+
+    /// Name of the fiscal year the period is in, e.g. `2023/24` for a year
+    /// starting in April 2023, or just the year for calendar years.
+    fn year(&self) -> String {
+        let offset = self.calendar.year_start as i32 - 1;
+        let year = (self.month - (self.month - offset).rem_euclid(12)).div_euclid(12);
+        if offset == 0 {
+            year.to_string()
+        } else {
+            format!("{year}/{:02}", (year + 1) % 100)
+        }
+    }
+}
+
+impl Display for Period {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.granularity {
+            Granularity::Week => {
+                let week = self.start.iso_week();
+                write!(f, "{}-W{:02}", week.year(), week.week())
+            }
+            Granularity::Month => write!(f, "{}", first_day(self.month).format("%b %Y")),
+            Granularity::Quarter => {
+                let offset = self.calendar.year_start as i32 - 1;
+                let quarter = (self.month - offset).rem_euclid(12) / 3 + 1;
+                let year = self.year();
+                // Keep the header within a column
+                write!(
+                    f,
+                    "Q{quarter} {}",
+                    year.get(2..).filter(|_| offset != 0).unwrap_or(&year)
+                )
+            }
+            Granularity::Year => write!(f, "{}", self.year()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn periods_and_their_labels() {
+        let calendar = Calendar::default();
+        let periods = Period::between(
+            date(2022, 11, 20),
+            date(2023, 2, 1),
+            Granularity::Month,
+            calendar,
+        );
+        assert_eq!(
+            vec!["Nov 2022", "Dec 2022", "Jan 2023", "Feb 2023"],
+            periods.iter().map(|p| p.to_string()).collect::<Vec<_>>()
+        );
+        assert_eq!(date(2023, 2, 28), *periods[3].end());
+
+        let first = periods[0].trim(date(2022, 11, 20), date(2023, 2, 1));
+        assert_eq!((date(2022, 11, 20), 11), (first.start, first.days()));
+        assert_eq!("Nov 2022", first.to_string());
+
+        let week = Period::containing(date(2023, 1, 1), Granularity::Week, calendar);
+        assert_eq!(
+            (date(2022, 12, 26), date(2023, 1, 1)),
+            (week.start, week.end)
+        );
+        assert_eq!("2022-W52", week.to_string());
+
+        let quarter = Period::containing(date(2023, 8, 15), Granularity::Quarter, calendar);
+        assert_eq!(
+            (date(2023, 7, 1), date(2023, 9, 30)),
+            (quarter.start, quarter.end)
+        );
+        assert_eq!("Q3 2023", quarter.to_string());
+
+        assert_eq!(
+            "2024",
+            Period::containing(date(2023, 6, 1), Granularity::Year, calendar)
+                .next()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn pay_cycles_and_fiscal_years() {
+        let payday = Calendar::new(MonthStart::Day(25), 4);
+
+        let month = Period::containing(date(2023, 2, 10), Granularity::Month, payday);
+        assert_eq!(
+            (date(2023, 1, 25), date(2023, 2, 24)),
+            (month.start, month.end)
+        );
+        assert_eq!("Feb 2023", month.to_string());
+        assert_eq!(31, month.days());
+        assert_eq!("Mar 2023", month.next().to_string());
+
+        let year = Period::containing(date(2023, 3, 26), Granularity::Year, payday);
+        assert_eq!(
+            (date(2023, 3, 25), date(2024, 3, 24)),
+            (year.start, year.end)
+        );
+        assert_eq!("2023/24", year.to_string());
+
+        let quarter = Period::containing(date(2024, 1, 1), Granularity::Quarter, payday);
+        assert_eq!("Q4 23/24", quarter.to_string());
+
+        // 31 March 2023 is a Friday and 30 April 2023 a Sunday
+        let working_day = Calendar::new(MonthStart::LastWorkingDay, 1);
+        let month = Period::containing(date(2023, 4, 15), Granularity::Month, working_day);
+        assert_eq!(
+            (date(2023, 3, 31), date(2023, 4, 27)),
+            (month.start, month.end)
+        );
+        assert_eq!("Apr 2023", month.to_string());
+
+        assert!("32".parse::<MonthStart>().is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+};
+
+use clap::{Args, ValueEnum};
+use colored::Colorize;
+
+use crate::{
+    calc::{
+        prompt::{Answer, Prompt},
+        Categorizer,
+    },
+    import::{self, Format, ImportArgs, ImportOptions},
+    splits, Record,
+};
+
+/// What to do when the category in a file differs from the one the rules or
+/// the lookup give.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Conflict {
+    /// Keep the category in the file, and store it in the lookup.
+    File,
+    /// Use the category from the rules or the lookup.
+    #[default]
+    Lookup,
+    /// Ask which category to use.
+    Ask,
+}
+
+/// Arguments for applying the current rules and lookup to categorized files.
+#[derive(Debug, Args)]
+pub struct RecategorizeArgs {
+    #[arg(required = true)]
+    files: Vec<String>,
+    #[arg(long, value_enum, default_value_t)]
+    conflict: Conflict,
+    /// Only show the changes, without writing them.
+    #[arg(long)]
+    dry_run: bool,
+    #[command(flatten)]
+    import: ImportArgs,
+}
+
+/// Apply the current rules and lookup to the records that already have a
+/// category, and show the changed records of each file.
+pub fn run(args: &RecategorizeArgs, categorizer: &mut Categorizer) -> Result<(), Box<dyn Error>> {
+    if args.conflict == Conflict::Ask && !categorizer.interactive() {
+        return Err("Conflicts cannot be asked when running non-interactively".into());
+    }
+
+    let opts: ImportOptions = (&args.import).try_into()?;
+    let mut prompt: Option<Prompt> = None;
+    // Answers given for all the records with the same payee
+    let mut answers: HashMap<String, String> = HashMap::new();
+    // Categories the records of each payee end up with, and the one in the lookup
+    let mut votes: BTreeMap<String, Votes> = BTreeMap::new();
+
+    for filename in args.files.iter() {
+        let statement = import::read_statement(filename, &opts)?;
+        let format = *statement.format();
+        let mut records = statement.into_records();
+        let mut changed = 0;
+
+        println!("{}", filename.bold());
+        for record in records.iter_mut().filter(|r| opts.includes(r)) {
+            categorizer.normalize(record);
+            let (Some(current), Some(known)) = (
+                record.category().clone(),
+                categorizer.known_category(record),
+            ) else {
+                continue;
+            };
+            if splits::parse(&current).is_some() {
+                continue;
+            }
+
+            let category = match args.conflict {
+                _ if current == known => current.clone(),
+                Conflict::File => current.clone(),
+                Conflict::Lookup => known.clone(),
+                Conflict::Ask => match answers.get(record.get_payee()) {
+                    Some(category) => category.to_owned(),
+                    None => {
+                        let prompt = match prompt.as_mut() {
+                            Some(prompt) => prompt,
+                            None => prompt.insert(Prompt::new(categorizer.categories())?),
+                        };
+                        let (category, all) = ask(prompt, record, &current, known.clone())?;
+                        if all {
+                            answers.insert(record.get_payee().to_owned(), category.clone());
+                        }
+                        category
+                    }
+                },
+            };
+
+            if category != current {
+                println!(
+                    "  {} {:<40}{} -> {}",
+                    record.date().to_string().cyan(),
+                    record.description(),
+                    current.red(),
+                    category.green()
+                );
+                record.set_category(category.clone());
+                changed += 1;
+            }
+            if categorizer.storable(record) {
+                let (_, categories) = votes
+                    .entry(record.get_payee().to_owned())
+                    .or_insert_with(|| (known, BTreeMap::new()));
+                categories
+                    .entry(category)
+                    .or_insert_with(|| (0, record.clone()))
+                    .0 += 1;
+            }
+        }
+
+        println!("  {changed} records changed");
+        if changed > 0 && !args.dry_run {
+            match format {
+                Format::Csv => {
+                    import::write_csv(filename, &records, &opts)?;
+                }
+                _ => println!("  Only the lookup is updated, as it is not a CSV file"),
+            }
+        }
+    }
+
+    update_lookup(categorizer, votes, args.dry_run);
+
+    Ok(())
+}
+
+/// The category in the lookup, and the number of records and a record for each
+/// category the records of a payee end up with.
+type Votes = (String, BTreeMap<String, (usize, Record)>);
+
+/// Store the category most of the records of each payee end up with in the
+/// lookup, preferring the one already in it on a tie, and show the changes.
+fn update_lookup(categorizer: &mut Categorizer, votes: BTreeMap<String, Votes>, dry_run: bool) {
+    println!("{}", "Lookup".bold());
+    let mut updated = 0;
+    for (payee, (known, categories)) in votes {
+        let Some((category, (_, record))) = categories.iter().max_by(|(a, (x, _)), (b, (y, _))| {
+            x.cmp(y)
+                .then_with(|| (*a == &known).cmp(&(*b == &known)))
+                .then_with(|| b.cmp(a))
+        }) else {
+            continue;
+        };
+        if categories.len() > 1 {
+            let counts: Vec<String> = categories
+                .iter()
+                .map(|(c, (n, _))| format!("{c} ({n})"))
+                .collect();
+            println!(
+                "  {payee:<40}{} {}, using {}",
+                "records disagree:".yellow(),
+                counts.join(", "),
+                category
+            );
+        }
+        if *category != known {
+            println!("  {payee:<40}{} -> {}", known.red(), category.green());
+            updated += 1;
+            if !dry_run {
+                categorizer.overwrite(record);
+            }
+        }
+    }
+
+    match dry_run {
+        true => println!("  {updated} entries would be changed"),
+        false => println!("  {updated} entries changed"),
+    }
+}
+
+/// Ask which category to use for the record, and whether to use it for all the
+/// records with the same payee.
+fn ask(
+    prompt: &mut Prompt,
+    record: &Record,
+    current: &str,
+    known: String,
+) -> Result<(String, bool), Box<dyn Error>> {
+    let question = format!(
+        "Conflicting categories for {} - {}",
+        record.date().to_string().cyan(),
+        record.description().green()
+    );
+    let choices = [
+        (current.to_owned(), "file".to_string()),
+        (known, "lookup".to_string()),
+    ];
+
+    loop {
+        match prompt.ask(&question, &choices)? {
+            Answer::Category(category) => return Ok((category, false)),
+            Answer::All(category) => return Ok((category, true)),
+            Answer::Skip => return Ok((current.to_owned(), false)),
+            Answer::Undo => println!("Nothing to undo"),
+        }
+    }
+}
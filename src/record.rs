@@ -1,17 +1,30 @@
+use chrono::NaiveDate;
 use derive_getters::Getters;
+use derive_new::new;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
+use crate::splits::SplitOf;
+
 #[cfg(test)]
 use fake::{Dummy, Fake};
 
 /// A record matching the headers from `sample.csv`.
 /// Used to read and deserialize content from similar financial csv files.
-#[cfg_attr(test, derive(Dummy, derive_new::new))]
-#[derive(Debug, Deserialize, Serialize, Getters, Clone, PartialEq)]
+#[cfg_attr(test, derive(Dummy))]
+#[derive(Debug, Deserialize, Serialize, Getters, Clone, PartialEq, new)]
 pub struct Record {
-    #[serde(rename = "Transaction Date")]
-    date: String,
+    #[serde(rename = "Transaction Date", with = "crate::date::csv_format")]
+    date: NaiveDate,
+    #[serde(rename = "Transaction Type", default)]
+    #[new(default)]
+    transaction_type: Option<String>,
+    #[serde(rename = "Sort Code", default)]
+    #[new(default)]
+    sort_code: Option<String>,
+    #[serde(rename = "Account Number", default)]
+    #[new(default)]
+    account_number: Option<String>,
     #[serde(
         rename = "Transaction Description",
         deserialize_with = "deserialize_string_and_trim"
@@ -21,45 +34,218 @@ pub struct Record {
     debit_amount: Option<Decimal>,
     #[serde(rename = "Credit Amount", with = "rust_decimal::serde::float_option")]
     credit_amount: Option<Decimal>,
-    // #[serde(rename = "Balance")]
-    // balance: String,
+    #[serde(
+        rename = "Balance",
+        with = "rust_decimal::serde::float_option",
+        default
+    )]
+    #[new(default)]
+    balance: Option<Decimal>,
+    #[serde(rename = "Currency", default)]
+    #[new(default)]
+    currency: Option<String>,
+    /// Exchange rate from the currency of the record to the reporting currency.
+    #[serde(skip)]
+    #[new(default)]
+    rate: Option<Decimal>,
+    /// Canonical payee made from the description.
+    #[serde(skip)]
+    #[new(default)]
+    payee: Option<String>,
     #[serde(rename = "Category")]
     category: Option<String>,
+    /// The transaction this record is a part of, if it has been split.
+    #[serde(skip)]
+    #[new(default)]
+    split: Option<SplitOf>,
+    /// Markers such as `transfer`, stored as a `;` separated list.
+    #[serde(rename = "Tags", with = "tags_format", default)]
+    #[new(default)]
+    tags: Vec<String>,
+    /// Id given to the transaction by the bank, e.g. the OFX `FITID`.
+    #[serde(skip)]
+    #[new(default)]
+    bank_id: Option<String>,
+    /// Number of identical transactions before this one in its statement.
+    #[serde(skip)]
+    #[new(default)]
+    occurrence: usize,
 }
 
 impl Record {
+    /// The amount in the reporting currency.
     pub fn get_amount(&self) -> Decimal {
+        self.get_original_amount() * self.rate.unwrap_or(Decimal::ONE)
+    }
+
+    /// The amount in the currency of the statement.
+    pub fn get_original_amount(&self) -> Decimal {
         self.debit_amount
             .map(|x| -x)
             .or(self.credit_amount)
             .unwrap_or(Decimal::ZERO)
     }
 
+    /// Label of the account the record belongs to, made from the sort code
+    /// and account number.
+    pub fn account(&self) -> Option<String> {
+        let parts: Vec<&str> = [&self.sort_code, &self.account_number]
+            .into_iter()
+            .filter_map(|x| x.as_deref())
+            .map(|x| x.trim_start_matches('\'').trim())
+            .filter(|x| !x.is_empty())
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+
+    /// Check whether the record belongs to the account, given either by its
+    /// full label or just the account number.
+    pub fn is_in_account(&self, account: &str) -> bool {
+        self.account().as_deref() == Some(account)
+            || self
+                .account_number
+                .as_deref()
+                .map(|n| n.trim_start_matches('\'').trim() == account)
+                .unwrap_or(false)
+    }
+
+    pub fn set_transaction_type(&mut self, transaction_type: Option<String>) {
+        self.transaction_type = transaction_type;
+    }
+
+    pub fn set_sort_code(&mut self, sort_code: Option<String>) {
+        self.sort_code = sort_code;
+    }
+
+    pub fn set_account_number(&mut self, account_number: Option<String>) {
+        self.account_number = account_number;
+    }
+
+    pub fn set_balance(&mut self, balance: Option<Decimal>) {
+        self.balance = balance;
+    }
+
+    /// Stable identifier of the transaction. This is the id given by the bank
+    /// if the statement has one, otherwise it is made from the date, description,
+    /// amount and account, and the number of identical transactions before it in
+    /// the statement. Stays the same across runs and versions, but as it is a 64
+    /// bit hash, two transactions can share one, although it is very unlikely.
+    pub fn id(&self) -> String {
+        let key = match &self.bank_id {
+            Some(bank_id) => format!("{}|{bank_id}", self.account().unwrap_or_default()),
+            None => {
+                let key = format!(
+                    "{}|{}|{}|{}",
+                    self.date,
+                    self.description,
+                    self.get_original_amount().normalize(),
+                    self.account().unwrap_or_default()
+                );
+                // The first one keeps the id it had before occurrences were counted
+                match self.occurrence {
+                    0 => key,
+                    n => format!("{key}|{n}"),
+                }
+            }
+        };
+        // 64 bit FNV-1a, as the hash of the standard library is not guaranteed to be stable.
+        let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("{hash:016x}")
+    }
+
+    pub fn set_bank_id(&mut self, bank_id: Option<String>) {
+        self.bank_id = bank_id;
+    }
+
+    pub fn set_occurrence(&mut self, occurrence: usize) {
+        self.occurrence = occurrence;
+    }
+
+    /// The canonical payee if the record has been normalized, otherwise the description.
+    pub fn get_payee(&self) -> &str {
+        self.payee.as_deref().unwrap_or(&self.description)
+    }
+
+    pub fn set_payee(&mut self, payee: String) {
+        self.payee = Some(payee);
+    }
+
+    pub fn set_currency(&mut self, currency: Option<String>) {
+        self.currency = currency;
+    }
+
+    pub fn set_rate(&mut self, rate: Decimal) {
+        self.rate = Some(rate);
+    }
+
     pub fn set_category(&mut self, category: String) {
         self.category = Some(category);
     }
-}
 
-#[cfg_attr(test, derive(Dummy, derive_new::new))]
-#[derive(Debug, Deserialize, Serialize, Getters, Clone, PartialEq)]
-#[serde(rename_all = "PascalCase")]
-pub struct CreditRecord {
-    date: String,
-    #[serde(deserialize_with = "deserialize_string_and_trim")]
-    description: String,
-    amount: Decimal,
-}
+    pub fn clear_category(&mut self) {
+        self.category = None;
+    }
+
+    /// Set the amount in the currency of the statement, where negative amounts are debits.
+    pub fn set_amount(&mut self, amount: Decimal) {
+        if amount.is_sign_negative() {
+            self.debit_amount = Some(-amount);
+            self.credit_amount = None;
+        } else {
+            self.debit_amount = None;
+            self.credit_amount = Some(amount);
+        }
+    }
+
+    pub fn set_split(&mut self, split: SplitOf) {
+        self.split = Some(split);
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
 
-impl From<CreditRecord> for Record {
-    fn from(val: CreditRecord) -> Self {
-        Self {
-            date: val.date,
-            description: val.description,
-            debit_amount: Some(val.amount),
-            credit_amount: None,
-            category: None,
+    pub fn add_tag(&mut self, tag: impl Into<String>) {
+        let tag = tag.into();
+        if !self.has_tag(&tag) {
+            self.tags.push(tag);
         }
     }
+
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        self.tags = tags;
+    }
+}
+
+/// Serde helpers to store a list of tags in a single CSV field.
+pub(crate) mod tags_format {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(tags: &[String], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&tags.join(";"))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: Option<&str> = Deserialize::deserialize(deserializer)?;
+        Ok(parse(s.unwrap_or_default()))
+    }
+
+    pub fn parse(value: &str) -> Vec<String> {
+        value
+            .split(';')
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .map(|t| t.to_string())
+            .collect()
+    }
 }
 
 fn deserialize_string_and_trim<'de, D>(deserializer: D) -> Result<String, D::Error>
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use derive_getters::Getters;
+use rust_decimal::Decimal;
+
+use crate::{merge::transfers::TRANSFER, Record};
+
+/// Tag set on credits that are matched as the refund of an earlier debit.
+pub const REFUND: &str = "refund";
+
+/// Category of credits that can be linked as refunds, along with uncategorized ones.
+const INCOME: &str = "Income";
+
+/// A credit linked to the debit it refunds.
+#[derive(Debug, Clone, PartialEq, Getters)]
+pub struct Refund {
+    original: Record,
+    refund: Record,
+}
+
+/// Find and tag refunds, e.g. returning an order or a reversed card payment.
+///
+/// A credit is linked to an earlier debit from the same payee, dated at most
+/// `window` days before it, with the same or a larger amount. If there are several
+/// candidates the most recent one is used. A debit can be refunded in several
+/// parts, as long as the refunds do not add up to more than the debit.
+/// Only credits without a category, in `Income` or already in the category of the
+/// debit are linked, so other categories set on purpose, e.g. for a friend paying
+/// back a shared bill, are kept. The lookup gives credits the category of the
+/// payee, which is the same as the one of its debits. The refunds get the category of their original, so they are netted against it.
+pub fn link(records: &mut [Record], window: i64) -> Vec<Refund> {
+    let mut remaining: Vec<Decimal> = records.iter().map(|r| -r.get_amount()).collect();
+    let mut credits: Vec<usize> = (0..records.len())
+        .filter(|i| records[*i].get_amount().is_sign_positive())
+        .filter(|i| !records[*i].get_amount().is_zero() && !records[*i].has_tag(TRANSFER))
+        .collect();
+    credits.sort_by_key(|i| *records[*i].date());
+
+    // The debits of each payee, sorted by date
+    let mut debits: HashMap<&str, Vec<usize>> = HashMap::new();
+    for i in 0..records.len() {
+        if remaining[i].is_sign_positive()
+            && !remaining[i].is_zero()
+            && !records[i].has_tag(TRANSFER)
+        {
+            debits.entry(records[i].get_payee()).or_default().push(i);
+        }
+    }
+    debits
+        .values_mut()
+        .for_each(|d| d.sort_by_key(|i| *records[*i].date()));
+
+    let mut links = Vec::new();
+    for credit in credits {
+        let amount = records[credit].get_amount();
+        let date = *records[credit].date();
+        let category = records[credit].category().as_deref();
+        let is_income = match category {
+            Some(category) => category == INCOME || category.starts_with(&format!("{INCOME}/")),
+            None => true,
+        };
+        let Some(candidates) = debits.get(records[credit].get_payee()) else {
+            continue;
+        };
+        let end = candidates.partition_point(|i| *records[*i].date() <= date);
+        let original = candidates[..end]
+            .iter()
+            .rev()
+            .take_while(|i| (date - *records[**i].date()).num_days() <= window)
+            .filter(|i| is_income || records[**i].category().as_deref() == category)
+            .find(|i| remaining[**i] >= amount)
+            .copied();
+
+        if let Some(original) = original {
+            remaining[original] -= amount;
+            links.push((original, credit));
+        }
+    }
+
+    links
+        .into_iter()
+        .map(|(original, credit)| {
+            if let Some(category) = records[original].category().clone() {
+                records[credit].set_category(category);
+            }
+            records[credit].add_tag(REFUND);
+            Refund {
+                original: records[original].clone(),
+                refund: records[credit].clone(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn record(day: u32, description: &str, amount: i64, category: &str) -> Record {
+        let amount = Decimal::new(amount, 0);
+        Record::new(
+            NaiveDate::from_ymd_opt(2023, 1, day).unwrap(),
+            description.to_string(),
+            amount.is_sign_negative().then_some(-amount),
+            amount.is_sign_positive().then_some(amount),
+            Some(category.to_string()),
+        )
+    }
+
+    #[test]
+    fn link_credits_to_earlier_debits_from_the_same_payee() {
+        let mut records = vec![
+            record(2, "AMAZON", -40, "Home"),
+            record(3, "AMAZON", -10, "Gifts"),
+            record(5, "AMAZON", 25, "Income"),
+            record(6, "AMAZON", 20, "Income"),
+            record(7, "ZARA", 30, "Income"),
+            record(8, "AMAZON", 10, "Income"),
+            record(9, "AMAZON", 5, "Shared"),
+        ];
+
+        let links = link(&mut records, 30);
+
+        assert_eq!(
+            vec![(0, 2), (1, 5)],
+            links
+                .iter()
+                .map(|l| (
+                    records.iter().position(|r| r == l.original()).unwrap(),
+                    records.iter().position(|r| r == l.refund()).unwrap()
+                ))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some("Home"), records[2].category().as_deref());
+        assert!(records[2].has_tag(REFUND));
+        assert_eq!(Some("Gifts"), records[5].category().as_deref());
+        // More than what is left of either debit
+        assert!(!records[3].has_tag(REFUND));
+        assert!(!records[4].has_tag(REFUND));
+        // Categories other than income are kept
+        assert_eq!(Some("Shared"), records[6].category().as_deref());
+        assert!(!records[6].has_tag(REFUND));
+    }
+}
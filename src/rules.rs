@@ -0,0 +1,158 @@
+use std::{error::Error, fs};
+
+use derive_getters::Getters;
+use regex::Regex;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer};
+
+use crate::Record;
+
+/// Whether a rule applies to money going out or coming in.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Sign {
+    Debit,
+    Credit,
+}
+
+/// A rule assigning a category to the records matching all of its conditions.
+/// Text conditions ignore case, except for `regex` where `(?i)` can be used.
+/// The amount range is compared to the absolute amount.
+#[derive(Debug, Clone, Deserialize, Getters)]
+pub struct Rule {
+    #[serde(default)]
+    name: Option<String>,
+    category: String,
+    #[serde(default)]
+    contains: Option<String>,
+    #[serde(default)]
+    prefix: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_regex")]
+    regex: Option<Regex>,
+    #[serde(default)]
+    min_amount: Option<Decimal>,
+    #[serde(default)]
+    max_amount: Option<Decimal>,
+    #[serde(default)]
+    sign: Option<Sign>,
+}
+
+impl Rule {
+    pub fn matches(&self, record: &Record) -> bool {
+        let description = record.description().to_lowercase();
+        let amount = record.get_amount();
+
+        self.contains
+            .as_ref()
+            .map(|c| description.contains(&c.to_lowercase()))
+            .unwrap_or(true)
+            && self
+                .prefix
+                .as_ref()
+                .map(|p| description.starts_with(&p.to_lowercase()))
+                .unwrap_or(true)
+            && self
+                .regex
+                .as_ref()
+                .map(|r| r.is_match(record.description()))
+                .unwrap_or(true)
+            && self
+                .min_amount
+                .map(|min| amount.abs() >= min)
+                .unwrap_or(true)
+            && self
+                .max_amount
+                .map(|max| amount.abs() <= max)
+                .unwrap_or(true)
+            && self
+                .sign
+                .map(|sign| match sign {
+                    Sign::Debit => amount.is_sign_negative(),
+                    Sign::Credit => amount.is_sign_positive(),
+                })
+                .unwrap_or(true)
+    }
+}
+
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: Option<String> = Deserialize::deserialize(deserializer)?;
+    s.map(|s| Regex::new(&s).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Ordered list of categorization rules, where the first matching rule wins.
+#[derive(Debug, Clone, Default)]
+pub struct Rules(Vec<Rule>);
+
+impl Rules {
+    /// Load the rules from a JSON file. If the file does not exist, there are no rules.
+    pub fn new(filename: impl AsRef<str>) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(filename.as_ref()) {
+            Ok(content) => {
+                Ok(Self(serde_json::from_str(&content).map_err(|e| {
+                    format!("Invalid rules in {}: {e}", filename.as_ref())
+                })?))
+            }
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// The categories assigned by the rules.
+    pub fn categories(&self) -> impl Iterator<Item = &String> {
+        self.0.iter().map(|r| r.category())
+    }
+
+    /// Find the first rule matching the record, together with its position in the list.
+    pub fn find(&self, record: &Record) -> Option<(usize, &Rule)> {
+        self.0.iter().enumerate().find(|(_, r)| r.matches(record))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn record(description: &str, amount: i64) -> Record {
+        let amount = Decimal::new(amount, 2);
+        Record::new(
+            NaiveDate::from_ymd_opt(2023, 1, 31).unwrap(),
+            description.to_string(),
+            amount.is_sign_negative().then_some(-amount),
+            amount.is_sign_positive().then_some(amount),
+            None,
+        )
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = Rules(
+            serde_json::from_str(
+                r#"[
+                    { "category": "Food/Coffee", "prefix": "tesco", "max_amount": 5 },
+                    { "name": "Tesco", "category": "Food/Groceries", "contains": "TESCO" },
+                    { "category": "Income", "regex": "^ACME .*SALARY$", "sign": "credit" }
+                ]"#,
+            )
+            .unwrap(),
+        );
+
+        let find = |description, amount| {
+            rules
+                .find(&record(description, amount))
+                .map(|(i, r)| (i, r.category().as_str()))
+        };
+
+        assert_eq!(Some((0, "Food/Coffee")), find("TESCO STORES 3345", -350));
+        assert_eq!(
+            Some((1, "Food/Groceries")),
+            find("TESCO STORES 1021", -4200)
+        );
+        assert_eq!(Some((2, "Income")), find("ACME LTD SALARY", 250000));
+        assert_eq!(None, find("ACME LTD SALARY", -250000));
+    }
+}
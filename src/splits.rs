@@ -0,0 +1,212 @@
+use std::{error::Error, fs, str::FromStr};
+
+use derive_getters::Getters;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer};
+
+#[cfg(test)]
+use fake::{Dummy, Fake};
+
+use crate::{exclusions::Transaction, Record};
+
+/// A part of a split transaction. A part without an amount gets what is left
+/// of the transaction after the other parts.
+#[derive(Debug, Clone, PartialEq, Deserialize, Getters)]
+pub struct Part {
+    category: String,
+    /// Amount in the currency of the statement, which gets the sign of the transaction.
+    #[serde(default, deserialize_with = "deserialize_absolute")]
+    amount: Option<Decimal>,
+}
+
+fn deserialize_absolute<'de, D>(deserializer: D) -> Result<Option<Decimal>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<Decimal>::deserialize(deserializer)?.map(|a| a.abs()))
+}
+
+/// Links a part of a split transaction to the transaction it came from.
+#[cfg_attr(test, derive(Dummy))]
+#[derive(Debug, Clone, PartialEq, Getters)]
+pub struct SplitOf {
+    /// Id of the original transaction.
+    id: String,
+    /// Position of the part, starting from one.
+    part: usize,
+    parts: usize,
+    /// Amount of the original transaction.
+    total: Decimal,
+}
+
+/// A transaction split into parts, from the splits file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Split {
+    #[serde(flatten)]
+    transaction: Transaction,
+    parts: Vec<Part>,
+}
+
+/// Transactions split into parts, which take precedence over the category column.
+#[derive(Debug, Clone, Default)]
+pub struct Splits(Vec<Split>);
+
+impl Splits {
+    /// Load the splits from a JSON file. If the file does not exist, there are no splits.
+    pub fn new(filename: impl AsRef<str>) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(filename.as_ref()) {
+            Ok(content) => {
+                let splits: Vec<Split> = serde_json::from_str(&content)
+                    .map_err(|e| format!("Invalid splits in {}: {e}", filename.as_ref()))?;
+                for split in splits.iter() {
+                    split.transaction.validate(filename.as_ref())?;
+                }
+                Ok(Self(splits))
+            }
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Find the parts of the record in the splits file.
+    pub fn find(&self, record: &Record) -> Option<&[Part]> {
+        self.0
+            .iter()
+            .find(|s| s.transaction.matches(record))
+            .map(|s| s.parts.as_slice())
+    }
+
+    /// Split the record into a record for each of its parts, given either in the
+    /// splits file or in its category. A record that is not split is returned as is.
+    pub fn distribute(&self, record: Record) -> Result<Vec<Record>, Box<dyn Error>> {
+        let parts = match self.find(&record) {
+            Some(parts) => parts.to_vec(),
+            None => match record.category().as_deref().and_then(parse) {
+                Some(parts) => parts?,
+                None => return Ok(vec![record]),
+            },
+        };
+
+        let total = record.get_original_amount();
+        let given: Decimal = parts.iter().filter_map(|p| p.amount).sum();
+        let missing = parts.iter().filter(|p| p.amount.is_none()).count();
+        let rest = total.abs() - given;
+        if missing > 1 || rest.is_sign_negative() || (missing == 0 && !rest.is_zero()) {
+            return Err(format!(
+                "The parts of {} on {} add up to {given}, instead of {} in the currency of the statement",
+                record.description(),
+                record.date(),
+                total.abs()
+            )
+            .into());
+        }
+
+        let id = record.id();
+        Ok(parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| {
+                let amount = part.amount.unwrap_or(rest);
+                let mut split = record.clone();
+                split.set_category(part.category.clone());
+                split.set_amount(if total.is_sign_negative() {
+                    -amount
+                } else {
+                    amount
+                });
+                split.set_split(SplitOf {
+                    id: id.clone(),
+                    part: i + 1,
+                    parts: parts.len(),
+                    total,
+                });
+                split
+            })
+            .collect())
+    }
+}
+
+/// Parse the split syntax of a category, e.g. `Food/Groceries=30;Home=15.50;Gifts`.
+/// Returns `None` if the category is not split.
+pub fn parse(category: &str) -> Option<Result<Vec<Part>, Box<dyn Error>>> {
+    if !category.contains('=') {
+        return None;
+    }
+
+    Some(
+        category
+            .split(';')
+            .map(|part| {
+                let (category, amount) = match part.split_once('=') {
+                    Some((category, amount)) => {
+                        let amount = Decimal::from_str(amount.trim()).map_err(|e| {
+                            format!("Invalid amount in split category '{part}': {e}")
+                        })?;
+                        (category, Some(amount.abs()))
+                    }
+                    None => (part, None),
+                };
+                Ok(Part {
+                    category: category.trim().to_string(),
+                    amount,
+                })
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn distribute_split_category() {
+        let record = Record::new(
+            NaiveDate::from_ymd_opt(2023, 1, 31).unwrap(),
+            "TESCO STORES".to_string(),
+            Some(Decimal::new(5000, 2)),
+            None,
+            Some("Food/Groceries=30;Home/Household=15.50;Gifts".to_string()),
+        );
+
+        let parts = Splits::default().distribute(record.clone()).unwrap();
+
+        assert_eq!(
+            vec![
+                ("Food/Groceries", Decimal::new(-30, 0)),
+                ("Home/Household", Decimal::new(-1550, 2)),
+                ("Gifts", Decimal::new(-450, 2)),
+            ],
+            parts
+                .iter()
+                .map(|r| (r.category().as_deref().unwrap(), r.get_amount()))
+                .collect::<Vec<_>>()
+        );
+        assert!(parts
+            .iter()
+            .all(|r| r.split().as_ref().unwrap().id() == &record.id()));
+
+        let mut record = record;
+        record.set_category("Food=30;Home=30".to_string());
+        assert!(Splits::default().distribute(record.clone()).is_err());
+
+        // Signed amounts in the splits file are the same as absolute ones
+        let splits = Splits(
+            serde_json::from_str(
+                r#"[{ "date": "31/01/2023", "description": "TESCO STORES", "amount": -50,
+                    "parts": [{ "category": "Food", "amount": -30 }, { "category": "Home" }] }]"#,
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            vec![Decimal::new(-30, 0), Decimal::new(-20, 0)],
+            splits
+                .distribute(record)
+                .unwrap()
+                .iter()
+                .map(|r| r.get_amount())
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -5,20 +5,79 @@ use std::{
     cell::{Ref, RefCell},
     collections::HashMap,
     error::Error,
-    fs,
     rc::Rc,
     str::Split,
 };
 
+use clap::Args;
+use derive_getters::Getters;
+use derive_new::new;
 use rust_decimal::Decimal;
 
-use crate::{calc::get_category, utils::Lookup, Record};
+use crate::{
+    calc::Categorizer,
+    exclusions::Exclusions,
+    import::{self, Format, ImportOptions},
+    merge::transfers::TRANSFER,
+    period::{Calendar, Granularity, Period},
+    refunds::{self, Refund},
+    splits::Splits,
+    utils::ignored_categories::IgnoredCategories,
+    Record,
+};
+
+/// Arguments controlling which records count towards the totals, and in which
+/// categories.
+#[derive(Debug, Args)]
+pub struct TotalsArgs {
+    /// JSON file with the transactions to keep out of the totals.
+    #[arg(long, default_value = "exclusions.json")]
+    exclusions: String,
+    /// JSON file with the transactions split into several categories.
+    #[arg(long, default_value = "splits.json")]
+    splits: String,
+    /// Maximum number of days between a debit and its refund.
+    #[arg(long, default_value = "90")]
+    refund_window: i64,
+    /// Do not match refunds to earlier debits.
+    #[arg(long)]
+    no_refunds: bool,
+}
+
+#[derive(Debug, Clone, new, Getters)]
+pub struct TotalsOptions {
+    exclusions: Exclusions,
+    splits: Splits,
+    /// Maximum number of days between a debit and its refund, if refunds are matched.
+    refund_window: Option<i64>,
+}
+
+impl TryFrom<&TotalsArgs> for TotalsOptions {
+    type Error = Box<dyn Error>;
+
+    fn try_from(value: &TotalsArgs) -> Result<Self, Self::Error> {
+        Ok(TotalsOptions::new(
+            Exclusions::new(&value.exclusions)?,
+            Splits::new(&value.splits)?,
+            (!value.no_refunds).then_some(value.refund_window),
+        ))
+    }
+}
 
 /// Represents the tree structure of expenses and income.
 #[derive(Debug, Default)]
 pub struct Tree {
     name: String,
     root: RefCell<Node>,
+    /// Records kept out of the tree by the exclusions.
+    excluded: Vec<Record>,
+    /// Credits matched as refunds of earlier debits.
+    refunds: Vec<Refund>,
+    /// The period the records are grouped by, if any.
+    period: Option<Period>,
+    /// Number of days covered, which is the length of the period, or the days
+    /// from the first to the last record of a file.
+    days: Option<i64>,
 }
 
 impl Tree {
@@ -26,6 +85,10 @@ impl Tree {
         Self {
             name,
             root: RefCell::default(),
+            excluded: Vec::new(),
+            refunds: Vec::new(),
+            period: None,
+            days: None,
         }
     }
 
@@ -33,7 +96,7 @@ impl Tree {
         filename
             .as_ref()
             .split('/')
-            .last()
+            .next_back()
             .unwrap_or_default()
             .split('_')
             .next()
@@ -41,38 +104,189 @@ impl Tree {
             .to_string()
     }
 
-    /// Load a tree from a file, and use the lookup to assign categories to the lines.
-    /// This will interatively ask the user for categories if none can be found in the provided lookup.
+    /// Load a tree from a file, and use the rules and lookup to assign categories to the lines.
+    /// This will interatively ask the user for categories if none can be found,
+    /// unless the categorizer runs non-interactively. If `write` is set, the new
+    /// categories are written back to the file when it is a CSV file.
     pub fn load_from_file(
         filename: impl AsRef<str>,
-        lookup: &mut Lookup,
+        categorizer: &mut Categorizer,
+        opts: &ImportOptions,
+        totals: &TotalsOptions,
+        write: bool,
     ) -> Result<Tree, Box<dyn Error>> {
-        let tmp = filename.as_ref().to_owned() + ".tmp";
+        let filenames = [filename.as_ref().to_owned()];
+        let mut trees = Self::load_from_files(&filenames, categorizer, opts, totals, write)?;
+        Ok(trees.remove(0))
+    }
 
-        let mut reader = csv::Reader::from_path(filename.as_ref())?;
-        let mut writer = csv::Writer::from_path(&tmp)?;
+    /// Load a tree for each of the files, like `load_from_file`. Refunds are
+    /// linked across the files, so they can be in a later file than the original.
+    pub fn load_from_files(
+        filenames: &[String],
+        categorizer: &mut Categorizer,
+        opts: &ImportOptions,
+        totals: &TotalsOptions,
+        write: bool,
+    ) -> Result<Vec<Tree>, Box<dyn Error>> {
+        let mut files = Vec::new();
+        for filename in filenames {
+            files.push(Self::read(filename, categorizer, opts, totals, write)?);
+        }
+        import::check_currencies(
+            files.iter().flat_map(|(i, e)| i.iter().chain(e.iter())),
+            opts,
+        )?;
+
+        let sizes: Vec<usize> = files.iter().map(|(i, _)| i.len()).collect();
+        let mut included: Vec<Record> = files
+            .iter_mut()
+            .flat_map(|(i, _)| std::mem::take(i))
+            .collect();
+        let refunds = Self::link_refunds(&mut included, totals);
+
+        let mut included = included.into_iter();
+        Ok(filenames
+            .iter()
+            .zip(files)
+            .zip(sizes)
+            .map(|((filename, (_, excluded)), size)| {
+                let mut tree = Self::new(Self::extract_name_from_file(filename));
+                let records: Vec<Record> = included.by_ref().take(size).collect();
+                let dates = records.iter().chain(excluded.iter()).map(|r| *r.date());
+                if let (Some(from), Some(to)) = (dates.clone().min(), dates.max()) {
+                    tree.days = Some((to - from).num_days() + 1);
+                }
+                tree.refunds = refunds
+                    .iter()
+                    .filter(|r| records.contains(r.refund()))
+                    .cloned()
+                    .collect();
+                tree.excluded = excluded;
+                records.into_iter().for_each(|record| tree.insert(record));
+                tree
+            })
+            .collect())
+    }
 
-        let tree = Self::new(Self::extract_name_from_file(filename.as_ref()));
+    /// Load the records of all the files, like `load_from_file`, and group them
+    /// into a tree for each period, named after the period. Periods without
+    /// records in between are included as empty trees.
+    pub fn load_by_period(
+        filenames: &[String],
+        categorizer: &mut Categorizer,
+        opts: &ImportOptions,
+        totals: &TotalsOptions,
+        write: bool,
+        granularity: Granularity,
+        calendar: Calendar,
+    ) -> Result<Vec<Tree>, Box<dyn Error>> {
+        let mut included = Vec::new();
+        let mut excluded = Vec::new();
+        for filename in filenames {
+            let (i, e) = Self::read(filename, categorizer, opts, totals, write)?;
+            included.extend(i);
+            excluded.extend(e);
+        }
+        import::check_currencies(included.iter().chain(excluded.iter()), opts)?;
+        // Refunds are linked before grouping, so they can be in a later period than the original.
+        let refunds = Self::link_refunds(&mut included, totals);
 
-        for result in reader.deserialize() {
-            let mut record: Record = result?;
-            if record.category().is_none() {
-                record.set_category(get_category(&record, lookup)?);
-            }
-            if let Some(category) = record.category() {
-                lookup.insert(record.description().to_owned(), category.to_owned());
+        let dates = included.iter().chain(excluded.iter()).map(|r| *r.date());
+        let (Some(from), Some(to)) = (dates.clone().min(), dates.max()) else {
+            return Ok(Vec::new());
+        };
+
+        // The first and last periods only count the days the records cover
+        Ok(Period::between(from, to, granularity, calendar)
+            .into_iter()
+            .map(|period| period.trim(from, to))
+            .map(|period| {
+                let mut tree = Self::new(period.to_string());
+                tree.period = Some(period);
+                tree.days = Some(period.days());
+                tree.excluded = excluded
+                    .extract_if(.., |r| period.contains(r.date()))
+                    .collect();
+                tree.refunds = refunds
+                    .iter()
+                    .filter(|r| period.contains(r.refund().date()))
+                    .cloned()
+                    .collect();
+                included
+                    .extract_if(.., |r| period.contains(r.date()))
+                    .for_each(|record| tree.insert(record));
+                tree
+            })
+            .collect())
+    }
+
+    /// Read the records of a file and categorize them, returning the records to
+    /// put in the tree and the ones kept out by the exclusions. Split records are
+    /// returned as their parts.
+    fn read(
+        filename: &str,
+        categorizer: &mut Categorizer,
+        opts: &ImportOptions,
+        totals: &TotalsOptions,
+        write: bool,
+    ) -> Result<(Vec<Record>, Vec<Record>), Box<dyn Error>> {
+        let statement = import::read_statement(filename, opts)?;
+        let format = *statement.format();
+        let mut records = statement.into_records();
+
+        records
+            .iter_mut()
+            .for_each(|record| categorizer.normalize(record));
+        categorizer.categorize_all(
+            &mut records
+                .iter_mut()
+                .filter(|record| opts.includes(record) && totals.splits().find(record).is_none())
+                .collect::<Vec<&mut Record>>(),
+        )?;
+
+        // Only CSV files can have the categories written back to them.
+        if write && format == Format::Csv {
+            import::write_csv(filename, &records, opts)?;
+        }
+
+        let mut included = Vec::new();
+        let mut excluded = Vec::new();
+
+        for mut record in records {
+            if !opts.includes(&record) {
+                continue;
             }
 
-            writer.serialize(record.clone())?;
+            match record.category() {
+                Some(_) => categorizer.remember(&record),
+                // The categories come from the splits file.
+                None if totals.splits().find(&record).is_some() => {}
+                // Left uncategorized, so it is kept in the file as it was.
+                None => match categorizer.placeholder() {
+                    Some(category) => record.set_category(category.to_owned()),
+                    None => continue,
+                },
+            }
 
-            // Tree
-            tree.insert(record);
+            let is_excluded = totals.exclusions().find(&record).is_some();
+            for record in totals.splits().distribute(record)? {
+                if is_excluded {
+                    excluded.push(record);
+                } else {
+                    included.push(record);
+                }
+            }
         }
 
-        writer.flush()?;
-        fs::rename(&tmp, filename.as_ref())?;
+        Ok((included, excluded))
+    }
 
-        Ok(tree)
+    fn link_refunds(records: &mut [Record], totals: &TotalsOptions) -> Vec<Refund> {
+        match totals.refund_window() {
+            Some(window) => refunds::link(records, *window),
+            None => Vec::new(),
+        }
     }
 
     pub fn insert(&self, record: Record) {
@@ -87,6 +301,29 @@ impl Tree {
         &self.name
     }
 
+    pub fn get_excluded(&self) -> &[Record] {
+        &self.excluded
+    }
+
+    pub fn get_refunds(&self) -> &[Refund] {
+        &self.refunds
+    }
+
+    pub fn get_period(&self) -> Option<&Period> {
+        self.period.as_ref()
+    }
+
+    /// Number of days the records in the tree cover, or the length of its period,
+    /// or `None` for a file without records.
+    pub fn get_days(&self) -> Option<i64> {
+        self.days
+    }
+
+    /// Whether the tree has no records, including excluded ones.
+    pub fn is_empty(&self) -> bool {
+        self.root.borrow().children.is_empty() && self.excluded.is_empty()
+    }
+
     pub fn preorder<F>(&self, action: F)
     where
         F: Fn(&Ref<Node>, usize) + Copy,
@@ -137,6 +374,8 @@ impl IntoIterator for Tree {
 #[derive(Debug, Default)]
 pub struct Node {
     category: String,
+    /// The full `/` separated category of the node.
+    path: String,
     children: HashMap<String, Rc<RefCell<Node>>>,
     records: Vec<Record>,
 }
@@ -174,12 +413,45 @@ impl Node {
         &self.category
     }
 
+    pub fn path(&self) -> &String {
+        &self.path
+    }
+
     pub fn total(&self) -> Decimal {
         self.children
             .values()
             .map(|c| c.borrow().total())
             .sum::<Decimal>()
-            + self.records.iter().map(|r| r.get_amount()).sum::<Decimal>()
+            + self.get_records().map(|r| r.get_amount()).sum::<Decimal>()
+    }
+
+    /// Whether the category and all of its subcategories are ignored.
+    pub fn is_ignored(&self, ignored_categories: &IgnoredCategories) -> bool {
+        ignored_categories.contains(&self.path)
+            && self
+                .children
+                .values()
+                .all(|c| c.borrow().is_ignored(ignored_categories))
+    }
+
+    /// The total of the categories that are not ignored, e.g. only a subcategory
+    /// that is included again. Nodes that are ignored as a whole keep their total.
+    pub fn total_without(&self, ignored_categories: &IgnoredCategories) -> Decimal {
+        if self.is_ignored(ignored_categories) {
+            return self.total();
+        }
+        let children = self
+            .children
+            .values()
+            .map(|c| c.borrow())
+            .filter(|c| !c.is_ignored(ignored_categories))
+            .map(|c| c.total_without(ignored_categories))
+            .sum::<Decimal>();
+        if ignored_categories.contains(&self.path) {
+            children
+        } else {
+            children + self.get_records().map(|r| r.get_amount()).sum::<Decimal>()
+        }
     }
 
     pub fn for_each<F>(&self, f: F)
@@ -192,13 +464,16 @@ impl Node {
         });
     }
 
+    /// The records of the node, leaving out transfers between accounts, which
+    /// are neither spent nor earned.
     pub fn get_records(&self) -> impl Iterator<Item = &Record> {
-        self.records.iter()
+        self.records.iter().filter(|r| !r.has_tag(TRANSFER))
     }
 
-    fn new(category: String) -> Self {
+    fn new(category: String, path: String) -> Self {
         Node {
             category,
+            path,
             children: HashMap::default(),
             records: Vec::default(),
         }
@@ -208,10 +483,14 @@ impl Node {
         fn helper(node: &RefCell<Node>, record: Record, mut splits: Split<char>) {
             if let Some(cat) = splits.next() {
                 let mut node = node.borrow_mut();
+                let path = match node.path.is_empty() {
+                    true => cat.to_string(),
+                    false => format!("{}/{cat}", node.path),
+                };
                 let child = node
                     .children
                     .entry(cat.to_string())
-                    .or_insert_with(|| Rc::new(RefCell::new(Node::new(cat.to_string()))));
+                    .or_insert_with(|| Rc::new(RefCell::new(Node::new(cat.to_string(), path))));
 
                 helper(child, record, splits);
             } else {
@@ -268,6 +547,7 @@ mod tests {
     fn node_without_children_eq() {
         let node = Node {
             category: Faker.fake(),
+            path: Faker.fake(),
             children: HashMap::default(),
             records: Vec::default(),
         };
@@ -278,6 +558,7 @@ mod tests {
     fn node_with_records_eq() {
         let node = Node {
             category: Faker.fake(),
+            path: Faker.fake(),
             children: HashMap::default(),
             records: vec![Faker.fake()],
         };
@@ -286,6 +567,7 @@ mod tests {
 
         let other = Node {
             category: Faker.fake(),
+            path: Faker.fake(),
             children: HashMap::default(),
             records: vec![Faker.fake()],
         };
@@ -301,6 +583,7 @@ mod tests {
             Faker.fake(),
             Rc::new(RefCell::new(Node {
                 category: Faker.fake(),
+                path: Faker.fake(),
                 children: HashMap::default(),
                 records: vec![Faker.fake(), Faker.fake()],
             })),
@@ -309,12 +592,14 @@ mod tests {
             Faker.fake(),
             Rc::new(RefCell::new(Node {
                 category: Faker.fake(),
+                path: Faker.fake(),
                 children: HashMap::default(),
                 records: vec![Faker.fake(), Faker.fake()],
             })),
         );
         let node = Node {
             category: Faker.fake(),
+            path: Faker.fake(),
             children,
             records: Vec::default(),
         };
@@ -327,6 +612,7 @@ mod tests {
             Faker.fake(),
             Rc::new(RefCell::new(Node {
                 category: Faker.fake(),
+                path: Faker.fake(),
                 children: HashMap::default(),
                 records: vec![Faker.fake(), Faker.fake()],
             })),
@@ -335,6 +621,7 @@ mod tests {
             Faker.fake(),
             Rc::new(RefCell::new(Node {
                 category: Faker.fake(),
+                path: Faker.fake(),
                 children: HashMap::default(),
                 records: vec![Faker.fake(), Faker.fake()],
             })),
@@ -343,15 +630,102 @@ mod tests {
             Faker.fake(),
             Rc::new(RefCell::new(Node {
                 category: Faker.fake(),
+                path: Faker.fake(),
                 children: HashMap::default(),
                 records: vec![Faker.fake(), Faker.fake()],
             })),
         );
         let other = Node {
             category: Faker.fake(),
+            path: Faker.fake(),
             children: other_children,
             records: Vec::default(),
         };
         assert_ne!(node, other);
     }
+
+    #[test]
+    fn only_count_categories_that_are_not_ignored() {
+        let tree = Tree::new("test".to_string());
+        for (category, amount) in [
+            ("Transfers/Card", 10),
+            ("Transfers/Savings", 30),
+            ("Food", 5),
+        ] {
+            tree.insert(Record::new(
+                Faker.fake(),
+                Faker.fake(),
+                Some(Decimal::new(amount, 0)),
+                None,
+                Some(category.to_string()),
+            ));
+        }
+        let ignored = IgnoredCategories::parse("Transfers\n!Transfers/Savings\nFood");
+        let root = tree.get_root().borrow();
+        let node = |name: &str| root.children.get(name).unwrap().borrow();
+
+        assert!(!node("Transfers").is_ignored(&ignored));
+        assert_eq!(
+            Decimal::new(-30, 0),
+            node("Transfers").total_without(&ignored)
+        );
+        assert!(node("Food").is_ignored(&ignored));
+        assert_eq!(Decimal::new(-5, 0), node("Food").total_without(&ignored));
+        assert_eq!(Decimal::new(-30, 0), root.total_without(&ignored));
+    }
+
+    #[test]
+    fn link_refunds_the_lookup_gave_the_category_of_the_debit() {
+        use crate::{
+            calc::NonInteractive, date::DateFormats, fx::FxRates, import::schema::Schemas,
+            normalize::Normalizer, rules::Rules, utils::Lookup,
+        };
+
+        let filename = std::env::temp_dir().join(format!("jan_{}.csv", std::process::id()));
+        std::fs::write(
+            &filename,
+            "Transaction Date,Transaction Description,Debit Amount,Credit Amount\n\
+             02/01/2023,AMAZON,40,\n\
+             05/01/2023,AMAZON,,25\n\
+             25/01/2023,ACME SALARY,,1000\n",
+        )
+        .unwrap();
+        let lookup = Lookup::from([
+            ("amazon".to_string(), "Home".to_string()),
+            ("acme salary".to_string(), "Income".to_string()),
+        ]);
+        let mut categorizer = Categorizer::new(lookup, Rules::default(), Normalizer::default());
+        categorizer.set_non_interactive(Some(NonInteractive::Fail), String::new());
+        let opts = ImportOptions::new(
+            Schemas::default(),
+            None,
+            DateFormats::default(),
+            None,
+            FxRates::default(),
+            Vec::new(),
+            Default::default(),
+        );
+        let totals = TotalsOptions::new(Default::default(), Default::default(), Some(90));
+
+        let tree = Tree::load_from_file(
+            filename.to_str().unwrap(),
+            &mut categorizer,
+            &opts,
+            &totals,
+            false,
+        );
+        std::fs::remove_file(&filename).unwrap();
+        let tree = tree.unwrap();
+
+        assert_eq!(1, tree.get_refunds().len());
+        let root = tree.get_root().borrow();
+        assert_eq!(
+            Decimal::new(-15, 0),
+            root.children.get("Home").unwrap().borrow().total()
+        );
+        assert_eq!(
+            Decimal::new(1000, 0),
+            root.children.get("Income").unwrap().borrow().total()
+        );
+    }
 }
@@ -8,11 +8,11 @@ use derive_new::new;
 use itertools::Itertools;
 use rust_decimal::{prelude::*, Decimal};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::Display,
 };
 
-const DAYS_IN_MONTH: usize = 30;
+const DAYS_IN_MONTH: i64 = 30;
 const HEADER_WIDTH: usize = 20;
 const COLUMN_WIDTH: usize = 10;
 const INCOME: &str = "Income";
@@ -23,6 +23,8 @@ pub struct CompareOptions {
     ignored_categories: IgnoredCategories,
     hide_ignored_categories: bool,
     number_of_columns: usize,
+    /// Also show the totals of each account.
+    by_account: bool,
 }
 
 #[derive(Debug)]
@@ -30,7 +32,11 @@ pub struct CompareTree<'a> {
     trees: &'a Vec<Tree>,
     categories: HashSet<String>,
     totals: Vec<TreeTotal>,
+    /// The totals of each account in each tree, if they are shown.
+    accounts: BTreeMap<String, Vec<TreeTotal>>,
     averages: HashMap<String, Decimal>,
+    /// Average number of days in the compared periods.
+    average_days: Decimal,
     options: CompareOptions,
 }
 
@@ -44,38 +50,53 @@ impl<'a> CompareTree<'a> {
                 }
             })
         });
-        let averages = Self::compute_averages(trees, &categories);
+        let averages = Self::compute_averages(trees, &categories, &options.ignored_categories);
+        // Files without any records are assumed to cover a month
+        let average_days = trees
+            .iter()
+            .map(|t| Decimal::from(t.get_days().unwrap_or(DAYS_IN_MONTH)))
+            .sum::<Decimal>()
+            .checked_div(Decimal::from(trees.len()))
+            .unwrap_or(Decimal::from(DAYS_IN_MONTH));
         let totals: Vec<TreeTotal> = trees
             .iter()
             .map(|t| TreeTotal::create_from(t, &options.ignored_categories))
             .collect();
+        let mut accounts: BTreeMap<String, Vec<TreeTotal>> = BTreeMap::new();
+        if options.by_account {
+            for (i, tree) in trees.iter().enumerate() {
+                for (account, total) in
+                    TreeTotal::create_by_account(tree, &options.ignored_categories)
+                {
+                    accounts
+                        .entry(account)
+                        .or_insert_with(|| vec![TreeTotal::default(); trees.len()])[i] = total;
+                }
+            }
+        }
 
         Self {
             trees,
             categories,
             totals,
+            accounts,
             averages,
+            average_days,
             options,
         }
     }
 
     fn compute_averages(
-        trees: &Vec<Tree>,
+        trees: &[Tree],
         categories: &HashSet<String>,
+        ignored_categories: &IgnoredCategories,
     ) -> HashMap<String, Decimal> {
         categories
             .iter()
             .map(|category| {
                 let avg = trees
                     .iter()
-                    .map(|t| {
-                        t.root
-                            .borrow()
-                            .children
-                            .get(category)
-                            .map(|n| n.borrow().total())
-                            .unwrap_or(Decimal::ZERO)
-                    })
+                    .map(|t| Self::category_total(t, category, ignored_categories))
                     .sum::<Decimal>()
                     / Decimal::from(trees.len());
                 (category, avg)
@@ -86,6 +107,33 @@ impl<'a> CompareTree<'a> {
             })
     }
 
+    /// The total of a top level category, only counting the subcategories that
+    /// are not ignored, unless the whole category is.
+    fn category_total(
+        tree: &Tree,
+        category: &str,
+        ignored_categories: &IgnoredCategories,
+    ) -> Decimal {
+        tree.root
+            .borrow()
+            .children
+            .get(category)
+            .map(|n| n.borrow().total_without(ignored_categories))
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    /// Whether the category is ignored in all the trees, along with its subcategories.
+    fn is_ignored(&self, category: &str) -> bool {
+        self.trees.iter().all(|t| {
+            t.root
+                .borrow()
+                .children
+                .get(category)
+                .map(|n| n.borrow().is_ignored(&self.options.ignored_categories))
+                .unwrap_or(true)
+        })
+    }
+
     fn output_category(&self, f: &mut std::fmt::Formatter<'_>, category: &str) -> std::fmt::Result {
         write!(f, "{category:<HEADER_WIDTH$}")?;
 
@@ -96,14 +144,7 @@ impl<'a> CompareTree<'a> {
         let totals = self
             .trees
             .iter()
-            .map(|t| {
-                t.root
-                    .borrow()
-                    .children
-                    .get(category)
-                    .map(|n| n.borrow().total())
-                    .unwrap_or(Decimal::ZERO)
-            })
+            .map(|t| Self::category_total(t, category, &self.options.ignored_categories))
             .rev()
             .take(self.options.number_of_columns)
             .rev();
@@ -137,7 +178,7 @@ impl<'a> CompareTree<'a> {
         let average_per_day = self
             .averages
             .get(category)
-            .map(|x| *x / Decimal::from(DAYS_IN_MONTH))
+            .map(|x| *x / self.average_days)
             .unwrap_or_default();
         write!(f, "{:>COLUMN_WIDTH$}", format_with_color(average_per_day))?;
         Ok(())
@@ -155,7 +196,8 @@ impl<'a> CompareTree<'a> {
             .copied()
             .unwrap_or_default()
             .abs()
-            / average_income;
+            .checked_div(average_income)
+            .unwrap_or_default();
         write!(
             f,
             "{:>width$} %",
@@ -178,14 +220,16 @@ impl<'a> CompareTree<'a> {
         write!(
             f,
             "{:>COLUMN_WIDTH$}",
-            format_with_color(total / Decimal::from(DAYS_IN_MONTH))
+            format_with_color(total / self.average_days)
         )?;
 
         let average_income = self.averages.get(INCOME).copied().unwrap_or_default();
         write!(
             f,
             "{:>width$} %",
-            format_with_color(Decimal::ONE_THOUSAND * (total / average_income)),
+            format_with_color(
+                Decimal::ONE_HUNDRED * total.checked_div(average_income).unwrap_or_default()
+            ),
             width = COLUMN_WIDTH - 2,
         )?;
 
@@ -226,10 +270,7 @@ impl Display for CompareTree<'_> {
             .categories
             .iter()
             .filter(|c| c.as_str() != INCOME)
-            .filter(|c| {
-                !self.options.hide_ignored_categories
-                    || !self.options.ignored_categories.contains(c)
-            })
+            .filter(|c| !self.options.hide_ignored_categories || !self.is_ignored(c))
             .sorted_by_cached_key(|c| {
                 self.averages
                     .get(c.as_str())
@@ -264,14 +305,7 @@ impl Display for CompareTree<'_> {
                 .zip(self.trees.iter())
                 .map(|(total, tree)| {
                     total.debits()
-                        - tree
-                            .get_root()
-                            .borrow()
-                            .children
-                            .get(HOME)
-                            .expect("there always to be a `Home` category")
-                            .borrow()
-                            .total()
+                        - Self::category_total(tree, HOME, &self.options.ignored_categories)
                 })
                 .rev()
                 .take(self.options.number_of_columns)
@@ -294,6 +328,22 @@ impl Display for CompareTree<'_> {
                 .as_slice(),
         )?;
 
+        // Output the amounts kept out by the exclusions, so they are not lost silently
+        if self.totals.iter().any(|t| !t.excluded().is_zero()) {
+            self.write_summary_row(
+                f,
+                "Excluded",
+                self.totals
+                    .iter()
+                    .map(|x| *x.excluded())
+                    .rev()
+                    .take(self.options.number_of_columns)
+                    .rev()
+                    .collect::<Vec<Decimal>>()
+                    .as_slice(),
+            )?;
+        }
+
         // Print saved in percentage
         write!(f, "{:<HEADER_WIDTH$}", "Percentage saved")?;
         write!(f, "{:COLUMN_WIDTH$}", "")?;
@@ -320,11 +370,92 @@ impl Display for CompareTree<'_> {
             write!(
                 f,
                 "{:>width$} %",
-                format_with_color(Decimal::ONE_HUNDRED * (t.total() / t.credits())),
+                format_with_color(t.percentage_saved()),
                 width = COLUMN_WIDTH - 2
             )?;
         }
 
+        // Output what was spent and saved in each account
+        if !self.accounts.is_empty() {
+            writeln!(f)?;
+        }
+        for (account, totals) in self.accounts.iter() {
+            writeln!(f)?;
+            let account = if account.is_empty() {
+                "Unknown"
+            } else {
+                account
+            };
+            writeln!(f, "{}", format!("Account: {account}").yellow())?;
+            let columns = |value: fn(&TreeTotal) -> Decimal| {
+                totals
+                    .iter()
+                    .map(value)
+                    .rev()
+                    .take(self.options.number_of_columns)
+                    .rev()
+                    .collect::<Vec<Decimal>>()
+            };
+            self.write_summary_row(f, "Spent", &columns(|t| *t.debits()))?;
+            self.write_summary_row(f, "Saved", &columns(|t| t.total()))?;
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::Record;
+
+    fn record(description: &str, amount: i64, category: &str) -> Record {
+        let amount = Decimal::new(amount, 0);
+        Record::new(
+            NaiveDate::from_ymd_opt(2023, 1, 31).unwrap(),
+            description.to_string(),
+            amount.is_sign_negative().then_some(-amount),
+            amount.is_sign_positive().then_some(amount),
+            Some(category.to_string()),
+        )
+    }
+
+    #[test]
+    fn percentages_of_the_average_income() {
+        colored::control::set_override(false);
+        let tree = Tree::new("jan".to_string());
+        tree.insert(record("SALARY", 1000, INCOME));
+        tree.insert(record("TESCO", -250, "Food"));
+        let trees = vec![tree];
+
+        let output = CompareTree::new(
+            &trees,
+            CompareOptions::new(Default::default(), false, 12, false),
+        )
+        .to_string();
+        let row = |title: &str| {
+            output
+                .lines()
+                .find(|l| l.starts_with(title))
+                .unwrap()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        assert_eq!("Income 1000 33 100 % 1000", row(INCOME));
+        assert_eq!("Food -250 -8 25 % -250", row("Food"));
+        assert_eq!("Saved 750 25 75 % 750", row("Saved"));
+
+        let output = CompareTree::new(
+            &trees,
+            CompareOptions::new(Default::default(), false, 12, true),
+        )
+        .to_string();
+        let account = output.split("Account: Unknown").nth(1).unwrap();
+        assert!(account.contains("Spent"));
+        assert!(account.contains("Saved"));
+    }
+}
@@ -1,9 +1,10 @@
-use std::{cell::RefCell, fmt::Display};
+use std::{cell::RefCell, collections::BTreeMap, fmt::Display};
 
 use derive_getters::Getters;
 use rust_decimal::Decimal;
 
 use crate::{
+    refunds::REFUND,
     utils::{format_with_color, ignored_categories::IgnoredCategories},
     Record, Tree,
 };
@@ -14,6 +15,8 @@ use crate::{
 pub struct TreeTotal {
     credits: Decimal,
     debits: Decimal,
+    /// Total of the records kept out by the exclusions.
+    excluded: Decimal,
 }
 
 impl TreeTotal {
@@ -22,31 +25,69 @@ impl TreeTotal {
     }
 
     pub fn percentage_saved(&self) -> Decimal {
+        if self.credits.is_zero() {
+            return Decimal::ZERO;
+        }
         Decimal::ONE_HUNDRED * (self.total() / self.credits())
     }
 
     pub fn create_from(tree: &Tree, ignored_categories: &IgnoredCategories) -> Self {
         let total = RefCell::new(TreeTotal::default());
 
+        tree.preorder(|node, _| {
+            node.get_records()
+                .filter(|r| !Self::ignore_record(r, ignored_categories))
+                .for_each(|record| total.borrow_mut().add(record))
+        });
+
+        let mut total = total.into_inner();
+        total.excluded = tree.get_excluded().iter().map(|r| r.get_amount()).sum();
+        total
+    }
+
+    /// Create a total for each account in the tree.
+    /// Records without an account are grouped under an empty name.
+    pub fn create_by_account(
+        tree: &Tree,
+        ignored_categories: &IgnoredCategories,
+    ) -> BTreeMap<String, Self> {
+        let totals = RefCell::new(BTreeMap::<String, TreeTotal>::new());
+
         tree.preorder(|node, _| {
             node.get_records()
                 .filter(|r| !Self::ignore_record(r, ignored_categories))
                 .for_each(|record| {
-                    total.borrow_mut().add(record.get_amount());
+                    totals
+                        .borrow_mut()
+                        .entry(record.account().unwrap_or_default())
+                        .or_default()
+                        .add(record);
                 })
         });
 
-        total.into_inner()
+        let mut totals = totals.into_inner();
+        for record in tree.get_excluded() {
+            totals
+                .entry(record.account().unwrap_or_default())
+                .or_default()
+                .excluded += record.get_amount();
+        }
+        totals
     }
 
-    fn add(&mut self, amount: Decimal) {
-        if amount.is_sign_positive() {
+    /// Add the amount of the record. Refunds are netted against the debits, so
+    /// they do not count as income.
+    fn add(&mut self, record: &Record) {
+        let amount = record.get_amount();
+        if amount.is_sign_positive() && !record.has_tag(REFUND) {
             self.credits += amount;
         } else {
             self.debits += amount;
         }
     }
 
+    /// Records in ignored categories are not part of the total. Transfers between
+    /// accounts are already left out by the nodes.
     fn ignore_record(record: &Record, ignored_categories: &IgnoredCategories) -> bool {
         record
             .category()
@@ -64,8 +105,11 @@ impl Display for TreeTotal {
         write!(
             f,
             "\tPercentage saved: {} %",
-            format_with_color(Decimal::ONE_HUNDRED * (self.total() / self.credits))
+            format_with_color(self.percentage_saved())
         )?;
+        if !self.excluded.is_zero() {
+            write!(f, "\nExcluded: {: >10}", format_with_color(self.excluded))?;
+        }
 
         Ok(())
     }
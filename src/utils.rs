@@ -38,6 +38,8 @@ pub struct AnalyzeOptions {
     print_items: bool,
     hide_ignored: bool,
     depth: Option<usize>,
+    show_original: bool,
+    group_by_payee: bool,
 }
 
 pub fn print_tree(tree: &Tree, total_tree: &TreeTotal, opts: &AnalyzeOptions) {
@@ -49,18 +51,24 @@ pub fn print_tree(tree: &Tree, total_tree: &TreeTotal, opts: &AnalyzeOptions) {
                 return;
             }
             let indent = TAB_SIZE * (depth - 1);
-            let is_ignored = opts.ignored_categories.contains(n.catogory());
+            // Categories with subcategories that are included again only count those
+            let is_ignored = n.is_ignored(&opts.ignored_categories);
+            let amount = n.total_without(&opts.ignored_categories);
 
             let total = if is_ignored {
                 if opts.hide_ignored {
                     return;
                 }
-                format!("{:.2}", n.total()).yellow()
+                format!("{amount:.2}").yellow()
             } else {
-                format_with_color(n.total())
+                format_with_color(amount)
             };
 
-            let percentage = Decimal::ONE_HUNDRED * (n.total() / total_tree.credits()).abs();
+            let percentage = if total_tree.credits().is_zero() {
+                Decimal::ZERO
+            } else {
+                Decimal::ONE_HUNDRED * (amount / total_tree.credits()).abs()
+            };
 
             println!(
                 // Alignment formatting. Using < to align front, and > to align end.
@@ -74,18 +82,43 @@ pub fn print_tree(tree: &Tree, total_tree: &TreeTotal, opts: &AnalyzeOptions) {
             );
 
             if opts.print_items {
-                // Print records
+                // Print records, with converted amounts grouped by their original currency
                 n.get_records()
-                    .fold(HashMap::<&String, Decimal>::new(), |mut acc, x| {
-                        acc.entry(x.description())
-                            .and_modify(|amount| *amount += x.get_amount())
-                            .or_insert(x.get_amount());
-                        acc
-                    })
+                    .fold(
+                        HashMap::<(String, Option<&String>), (Decimal, Decimal)>::new(),
+                        |mut acc, x| {
+                            let currency = x.rate().and(x.currency().as_ref());
+                            // Parts of split transactions are shown on their own
+                            let name = match x.split() {
+                                Some(split) => format!(
+                                    "{} [split {}/{} of {} #{}]",
+                                    x.description(),
+                                    split.part(),
+                                    split.parts(),
+                                    split.total(),
+                                    &split.id()[..8]
+                                ),
+                                None if opts.group_by_payee => x.get_payee().to_string(),
+                                None => x.description().to_string(),
+                            };
+                            let entry = acc
+                                .entry((name, currency))
+                                .or_insert((Decimal::ZERO, Decimal::ZERO));
+                            entry.0 += x.get_amount();
+                            entry.1 += x.get_original_amount();
+                            acc
+                        },
+                    )
                     .iter()
-                    .for_each(|(description, amount)| {
+                    .for_each(|((description, currency), (amount, original))| {
+                        let original = match currency {
+                            Some(currency) if opts.show_original => {
+                                format!(" ({} {currency})", format_with_color(*original))
+                            }
+                            _ => String::new(),
+                        };
                         println!(
-                            "{:<1$}{description:<2$}{amount:>10}",
+                            "{:<1$}{description:<2$}{amount:>10}{original}",
                             "",
                             TAB_SIZE + indent,
                             40 - (TAB_SIZE + indent),
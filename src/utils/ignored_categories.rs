@@ -1,25 +1,100 @@
-use std::{collections::HashSet, fs};
+use std::fs;
 
-/// Represents a set of categories that should be ignored.
+/// A line of the ignored categories file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern {
+    /// Whether the pattern re-includes categories ignored by earlier patterns.
+    negated: bool,
+    segments: Vec<String>,
+}
+
+impl Pattern {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, glob) = match line.strip_prefix('!') {
+            Some(glob) => (true, glob.trim()),
+            None => (false, line),
+        };
+
+        Some(Self {
+            negated,
+            segments: glob.split('/').map(|s| s.to_string()).collect(),
+        })
+    }
+
+    /// Whether the pattern matches the category or one of its parents.
+    fn matches(&self, category: &[&str]) -> bool {
+        (1..=category.len()).any(|n| matches_path(&self.segments, &category[..n]))
+    }
+}
+
+/// Match the segments of a pattern to those of a category, where `**` matches
+/// any number of segments.
+fn matches_path(pattern: &[String], category: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => category.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=category.len()).any(|n| matches_path(rest, &category[n..]))
+        }
+        Some((first, rest)) => match category.split_first() {
+            Some((segment, category)) => {
+                matches_segment(first.as_bytes(), segment.as_bytes())
+                    && matches_path(rest, category)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Match a single segment, where `*` matches any number of characters and `?`
+/// a single one.
+fn matches_segment(pattern: &[u8], segment: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => segment.is_empty(),
+        Some((b'*', rest)) => (0..=segment.len()).any(|n| matches_segment(rest, &segment[n..])),
+        Some((c, rest)) => match segment.split_first() {
+            Some((s, segment)) => (*c == b'?' || c == s) && matches_segment(rest, segment),
+            None => false,
+        },
+    }
+}
+
+/// Represents the categories that should be ignored.
+///
+/// Ignoring a category also ignores its subcategories. Patterns can use `*` to
+/// match any part of a category name and `**` to match any number of levels,
+/// e.g. `*/Reimbursed`. A pattern starting with `!` includes categories again,
+/// and the last pattern matching a category decides whether it is ignored.
 #[derive(Debug, Clone, Default)]
-pub struct IgnoredCategories(HashSet<String>);
+pub struct IgnoredCategories(Vec<Pattern>);
 
 impl IgnoredCategories {
     /// Create a new instance of `IgnoredCategories` from a filename.
-    /// This will read the content of the file and assume each line contains a category
-    /// that should be ignored.
+    /// This will read the content of the file and assume each line contains a pattern
+    /// of categories that should be ignored. Empty lines and lines starting with `#`
+    /// are skipped.
     pub fn new(filename: impl AsRef<str>) -> Self {
         let ignored_categories_text = fs::read_to_string(filename.as_ref()).unwrap_or_default();
-        IgnoredCategories(
-            ignored_categories_text
-                .lines()
-                .map(|l| l.to_string())
-                .collect::<HashSet<String>>(),
-        )
+        Self::parse(&ignored_categories_text)
+    }
+
+    /// Parse the patterns from the content of an ignored categories file.
+    pub fn parse(text: &str) -> Self {
+        IgnoredCategories(text.lines().filter_map(Pattern::parse).collect())
     }
 
-    pub fn contains(&self, key: &String) -> bool {
-        self.0.contains(key)
+    /// Whether the category, given as its full `/` separated path, is ignored.
+    pub fn contains(&self, category: &str) -> bool {
+        let category: Vec<&str> = category.split('/').collect();
+        self.0
+            .iter()
+            .rev()
+            .find(|p| p.matches(&category))
+            .map(|p| !p.negated)
+            .unwrap_or(false)
     }
 }
 
@@ -28,3 +103,32 @@ impl From<&str> for IgnoredCategories {
         Self::new(value)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ignore_subcategories_globs_and_negations() {
+        let ignored = IgnoredCategories(
+            [
+                "Transfers",
+                "!Transfers/Savings",
+                "# comment",
+                "*/Reimbursed",
+            ]
+            .iter()
+            .filter_map(|l| Pattern::parse(l))
+            .collect(),
+        );
+
+        assert!(ignored.contains("Transfers"));
+        assert!(ignored.contains("Transfers/Card"));
+        assert!(!ignored.contains("Transfers/Savings"));
+        assert!(!ignored.contains("Transfers/Savings/Pension"));
+        assert!(ignored.contains("Work/Reimbursed"));
+        assert!(ignored.contains("Work/Reimbursed/Travel"));
+        assert!(!ignored.contains("Reimbursed"));
+        assert!(!ignored.contains("Food"));
+    }
+}